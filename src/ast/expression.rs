@@ -1,5 +1,5 @@
-use crate::scanner::token::Token;
-use std::fmt;
+use crate::{interpreter::stmt::FunctionDefinition, scanner::token::Token};
+use std::{fmt, rc::Rc};
 
 #[derive(Debug)]
 pub enum Expr {
@@ -31,6 +31,48 @@ pub enum Expr {
         paren: Token,
         arguments: Vec<Expr>,
     },
+    List {
+        id: u32,
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
+    Lambda {
+        id: u32,
+        fun_def: Rc<FunctionDefinition>,
+    },
+    Map {
+        id: u32,
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    Index {
+        id: u32,
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    IndexSet {
+        id: u32,
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Get {
+        id: u32,
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        id: u32,
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    This {
+        id: u32,
+        keyword: Token,
+    },
     Grouping {
         id: u32,
         expression: Box<Expr>,
@@ -61,12 +103,42 @@ impl Expr {
             Expr::Binary { id, .. } => *id,
             Expr::Unary { id, .. } => *id,
             Expr::Call { id, .. } => *id,
+            Expr::List { id, .. } => *id,
+            Expr::Lambda { id, .. } => *id,
+            Expr::Map { id, .. } => *id,
+            Expr::Index { id, .. } => *id,
+            Expr::IndexSet { id, .. } => *id,
+            Expr::Get { id, .. } => *id,
+            Expr::Set { id, .. } => *id,
+            Expr::This { id, .. } => *id,
             Expr::Grouping { id, .. } => *id,
             Expr::Literal { id, .. } => *id,
             Expr::Variable { id, .. } => *id,
         }
     }
 
+    // the line of the leftmost token, which literals don't keep
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Expr::Assignment { name, .. } => Some(name.line),
+            Expr::Logical { left, .. } => left.line(),
+            Expr::Binary { left_expr, .. } => left_expr.line(),
+            Expr::Unary { token, .. } => Some(token.line),
+            Expr::Call { callee, .. } => callee.line(),
+            Expr::List { bracket, .. } => Some(bracket.line),
+            Expr::Lambda { fun_def, .. } => Some(fun_def.name.line),
+            Expr::Map { brace, .. } => Some(brace.line),
+            Expr::Index { object, .. }
+            | Expr::IndexSet { object, .. }
+            | Expr::Get { object, .. }
+            | Expr::Set { object, .. } => object.line(),
+            Expr::This { keyword, .. } => Some(keyword.line),
+            Expr::Grouping { expression, .. } => expression.line(),
+            Expr::Literal { .. } => None,
+            Expr::Variable { token, .. } => Some(token.line),
+        }
+    }
+
     pub fn assignment(id: u32, name: Token, value: Expr) -> Self {
         Expr::Assignment {
             id,
@@ -110,6 +182,65 @@ impl Expr {
         }
     }
 
+    pub fn list(id: u32, bracket: Token, elements: Vec<Expr>) -> Self {
+        Expr::List {
+            id,
+            bracket,
+            elements,
+        }
+    }
+
+    pub fn lambda(id: u32, fun_def: FunctionDefinition) -> Self {
+        Expr::Lambda {
+            id,
+            fun_def: Rc::new(fun_def),
+        }
+    }
+
+    pub fn map(id: u32, brace: Token, entries: Vec<(Expr, Expr)>) -> Self {
+        Expr::Map { id, brace, entries }
+    }
+
+    pub fn index(id: u32, object: Expr, bracket: Token, index: Expr) -> Self {
+        Expr::Index {
+            id,
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+        }
+    }
+
+    pub fn index_set(id: u32, object: Expr, bracket: Token, index: Expr, value: Expr) -> Self {
+        Expr::IndexSet {
+            id,
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            value: Box::new(value),
+        }
+    }
+
+    pub fn get(id: u32, object: Expr, name: Token) -> Self {
+        Expr::Get {
+            id,
+            object: Box::new(object),
+            name,
+        }
+    }
+
+    pub fn set(id: u32, object: Expr, name: Token, value: Expr) -> Self {
+        Expr::Set {
+            id,
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        }
+    }
+
+    pub fn this(id: u32, keyword: Token) -> Self {
+        Expr::This { id, keyword }
+    }
+
     pub fn grouping(id: u32, expression: Expr) -> Self {
         Expr::Grouping {
             id,
@@ -164,6 +295,24 @@ impl fmt::Display for Expr {
                 paren: _,
                 arguments,
             } => write!(f, "{callee}({:?})", arguments),
+            Expr::List { elements, .. } => write!(f, "{:?}", elements),
+            Expr::Lambda { fun_def, .. } => write!(f, "(fun {:?})", fun_def.params),
+            Expr::Map { entries, .. } => write!(f, "{:?}", entries),
+            Expr::Index { object, index, .. } => write!(f, "{object}[{index}]"),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => write!(f, "{object}[{index}] = {value}"),
+            Expr::Get { object, name, .. } => write!(f, "{object}.{}", name.lexeme),
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => write!(f, "{object}.{} = {value}", name.lexeme),
+            Expr::This { .. } => write!(f, "this"),
             Expr::Grouping { expression, .. } => write!(f, "(group {expression})"),
             Expr::Literal { value, .. } => write!(f, "{value}"),
             Expr::Variable { token, .. } => write!(f, "{token}"),
@@ -1,7 +1,9 @@
+use std::rc::Rc;
+
 use crate::{
     ast::expression::{Expr, LiteralValue},
     error::RuntimeSignal,
-    interpreter::stmt::Stmt,
+    interpreter::stmt::{CatchClause, FunctionDefinition, ImportStatement, Stmt, TryStatement},
     scanner::{
         token::{Literal, Token},
         token_type::TokenType,
@@ -16,13 +18,22 @@ pub struct Parser {
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_expr_id(tokens, 0)
+    }
+
+    // lets callers that parse several sources (e.g. the REPL) keep expr ids unique
+    pub fn with_expr_id(tokens: Vec<Token>, next_expr_id: u32) -> Self {
         Parser {
             tokens,
             current: 0,
-            next_expr_id: 0,
+            next_expr_id,
         }
     }
 
+    pub fn next_expr_id(&self) -> u32 {
+        self.next_expr_id
+    }
+
     pub fn parse(&mut self) -> (Vec<Stmt>, Vec<RuntimeSignal>) {
         let mut statements: Vec<Stmt> = Vec::new();
         let mut errors: Vec<RuntimeSignal> = Vec::new();
@@ -41,23 +52,111 @@ impl Parser {
 
     fn declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
         match self.peek().token_type {
-            TokenType::Fun => self.fun_declaration(),
+            TokenType::Class => self.class_declaration(),
+            // 'fun (' starts an anonymous function expression, not a declaration
+            TokenType::Fun if self.peek_next_type() != Some(TokenType::LeftParen) => {
+                self.fun_declaration()
+            }
             TokenType::Var => self.var_declaration(),
+            TokenType::Export => self.export_declaration(),
+            TokenType::Import => self.import_declaration(),
             _ => self.statement(),
         }
     }
 
-    fn fun_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
-        self.consume(TokenType::Fun, "'fun expected'".into())?;
+    fn export_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
+        self.advance();
+        match self.peek().token_type {
+            TokenType::Class | TokenType::Var => Ok(Stmt::Export(Box::new(self.declaration()?))),
+            TokenType::Fun if self.peek_next_type() == Some(TokenType::Identifier) => {
+                Ok(Stmt::Export(Box::new(self.fun_declaration()?)))
+            }
+            _ => Err(RuntimeSignal::static_error_at(
+                self.peek(),
+                "Expect a var, fun or class declaration after 'export'".into(),
+            )),
+        }
+    }
+
+    // 'from' is only special here, so it stays usable as an identifier elsewhere
+    fn import_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.advance();
+
+        let mut names = None;
+        if self.peek().token_type == TokenType::LeftBrace {
+            self.advance();
+            let mut listed = Vec::new();
+            loop {
+                listed.push(self.consume(TokenType::Identifier, "Expect imported name".into())?);
+
+                if self.peek().token_type == TokenType::Comma {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightBrace, "Expect '}' after imported names".into())?;
+
+            if self.peek().token_type != TokenType::Identifier || self.peek().lexeme != "from" {
+                return Err(RuntimeSignal::static_error_at(
+                    self.peek(),
+                    "Expect 'from' after imported names".into(),
+                ));
+            }
+            self.advance();
+            names = Some(listed);
+        }
+
+        let path = self.consume(TokenType::String, "Expect module path string".into())?;
+        self.consume(TokenType::Semicolon, "Expect ';' after import".into())?;
+
+        Ok(Stmt::Import(ImportStatement {
+            keyword,
+            path,
+            names,
+        }))
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
+        self.consume(TokenType::Class, "'class' expected".into())?;
 
         let name = self.consume(
             TokenType::Identifier,
-            "Expected function name after 'fun'".into(),
+            "Expected class name after 'class'".into(),
         )?;
 
+        self.consume(TokenType::LeftBrace, "Expect { before class body".into())?;
+
+        let mut methods = Vec::new();
+        while !self.is_at_end() && self.peek().token_type != TokenType::RightBrace {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect } after class body".into())?;
+
+        Ok(Stmt::class(name, methods))
+    }
+
+    fn fun_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
+        self.consume(TokenType::Fun, "'fun expected'".into())?;
+        self.function("function")
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, RuntimeSignal> {
+        let name = self.consume(TokenType::Identifier, format!("Expected {kind} name"))?;
+        let fun_def = self.function_definition(name, kind)?;
+        Ok(Stmt::Function(Rc::new(fun_def)))
+    }
+
+    // parameters and body, shared by declarations, methods and lambdas
+    fn function_definition(
+        &mut self,
+        name: Token,
+        kind: &str,
+    ) -> Result<FunctionDefinition, RuntimeSignal> {
         self.consume(
             TokenType::LeftParen,
-            "Expected '(' after function name".into(),
+            format!("Expected '(' after {kind} name"),
         )?;
 
         let mut parameters = Vec::new();
@@ -77,11 +176,14 @@ impl Parser {
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters".into())?;
 
-        self.consume(TokenType::LeftBrace, "Expect { before function body".into())?;
+        self.consume(
+            TokenType::LeftBrace,
+            format!("Expect {{ before {kind} body"),
+        )?;
 
         let body = self.block()?;
 
-        Ok(Stmt::function(name, parameters, body))
+        Ok(FunctionDefinition::new(name, parameters, body))
     }
     fn var_declaration(&mut self) -> Result<Stmt, RuntimeSignal> {
         self.advance();
@@ -115,7 +217,7 @@ impl Parser {
             }
             TokenType::While => {
                 self.advance();
-                self.while_statement()
+                self.while_statement(None)
             }
             TokenType::LeftBrace => {
                 self.advance();
@@ -123,13 +225,66 @@ impl Parser {
             }
             TokenType::For => {
                 self.advance();
-                self.for_statement()
+                self.for_statement(None)
             }
             TokenType::Return => self.return_statement(),
+            TokenType::Throw => self.throw_statement(),
+            TokenType::Try => {
+                self.advance();
+                self.try_statement()
+            }
+            TokenType::Break => self.break_statement(),
+            TokenType::Continue => self.continue_statement(),
+            TokenType::Identifier if self.peek_next_type() == Some(TokenType::Colon) => {
+                self.labeled_statement()
+            }
             _ => self.expression_statement(),
         }
     }
 
+    // label: while (...) ... / label: for (...) ...
+    fn labeled_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let label = self.advance();
+        self.advance(); // ':'
+
+        match self.peek().token_type {
+            TokenType::While => {
+                self.advance();
+                self.while_statement(Some(label))
+            }
+            TokenType::For => {
+                self.advance();
+                self.for_statement(Some(label))
+            }
+            _ => Err(RuntimeSignal::static_error_at(
+                self.peek(),
+                format!("Expected a loop after label '{}'", label.lexeme),
+            )),
+        }
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.advance();
+        let label = self.optional_label();
+        self.consume(TokenType::Semicolon, "Expect ';' after 'break'".into())?;
+        Ok(Stmt::Break(keyword, label))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.advance();
+        let label = self.optional_label();
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'".into())?;
+        Ok(Stmt::Continue(keyword, label))
+    }
+
+    fn optional_label(&mut self) -> Option<Token> {
+        if self.peek().token_type == TokenType::Identifier {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
     fn fresh_expr_id(&mut self) -> u32 {
         let id = self.next_expr_id;
         self.next_expr_id = self.next_expr_id.wrapping_add(1);
@@ -150,7 +305,8 @@ impl Parser {
         Ok(Stmt::Return(keyword, value))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.previous().clone();
         self.consume(
             TokenType::LeftParen,
             "Expected '(' after 'for'.".to_string(),
@@ -185,19 +341,13 @@ impl Parser {
 
         let body = self.statement()?;
 
-        let body = if let Some(inc) = increment {
-            Stmt::Block(vec![body, Stmt::Expression(inc)])
-        } else {
-            body
-        };
-
         let condition = if let Some(cond) = condition {
             cond
         } else {
             Expr::literal(self.fresh_expr_id(), LiteralValue::Boolean(true))
         };
 
-        let body = Stmt::while_statement(condition, body);
+        let body = Stmt::while_statement(keyword, label, condition, body, increment);
 
         match initalizer {
             Some(init) => Ok(Stmt::Block(vec![init, body])),
@@ -205,7 +355,8 @@ impl Parser {
         }
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.previous().clone();
         self.consume(
             TokenType::LeftParen,
             "Expected '(' after 'while'".to_string(),
@@ -218,10 +369,11 @@ impl Parser {
 
         let body = self.statement()?;
 
-        Ok(Stmt::while_statement(condition, body))
+        Ok(Stmt::while_statement(keyword, label, condition, body, None))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expected '(' after 'if'.".to_string())?;
         let condition = self.expression()?;
         self.consume(
@@ -239,10 +391,15 @@ impl Parser {
             _ => None,
         };
 
-        Ok(Stmt::if_statement(condition, then_branch, else_branch))
+        Ok(Stmt::if_statement(keyword, condition, then_branch, else_branch))
     }
 
     fn block(&mut self) -> Result<Stmt, RuntimeSignal> {
+        Ok(Stmt::Block(self.block_statements()?))
+    }
+
+    // the statements of a block whose '{' has already been consumed
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, RuntimeSignal> {
         let mut statements: Vec<Stmt> = Vec::new();
 
         while !self.is_at_end() && self.peek().token_type != TokenType::RightBrace {
@@ -254,13 +411,62 @@ impl Parser {
             "Expected '}' after block".to_string(),
         )?;
 
-        Ok(Stmt::Block(statements))
+        Ok(statements)
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.advance();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value".into())?;
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    // try { } catch (name) { } finally { }, with at least one of catch and finally
+    fn try_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'".into())?;
+        let body = self.block_statements()?;
+
+        let mut catch = None;
+        if self.peek().token_type == TokenType::Catch {
+            self.advance();
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'".into())?;
+            let name = self.consume(TokenType::Identifier, "Expect error variable name".into())?;
+            self.consume(TokenType::RightParen, "Expect ')' after error variable".into())?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body".into())?;
+            catch = Some(CatchClause {
+                name,
+                body: self.block_statements()?,
+            });
+        }
+
+        let mut finally = None;
+        if self.peek().token_type == TokenType::Finally {
+            self.advance();
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'".into())?;
+            finally = Some(self.block_statements()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(RuntimeSignal::static_error_at(
+                self.peek(),
+                "Expect 'catch' or 'finally' after try block".into(),
+            ));
+        }
+
+        Ok(Stmt::Try(TryStatement {
+            keyword,
+            body,
+            catch,
+            finally,
+        }))
     }
 
     fn print_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
+        let keyword = self.previous().clone();
         let expression = self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';' after value".to_string())?;
-        Ok(Stmt::Print(expression))
+        Ok(Stmt::Print(keyword, expression))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, RuntimeSignal> {
@@ -286,9 +492,26 @@ impl Parser {
                 Expr::Variable { token, .. } => {
                     return Ok(Expr::assignment(self.fresh_expr_id(), token, value))
                 }
+                Expr::Get { object, name, .. } => {
+                    return Ok(Expr::set(self.fresh_expr_id(), *object, name, value))
+                }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                    ..
+                } => {
+                    return Ok(Expr::index_set(
+                        self.fresh_expr_id(),
+                        *object,
+                        bracket,
+                        *index,
+                        value,
+                    ))
+                }
                 _ => {
-                    return Err(RuntimeSignal::static_error(
-                        token.line,
+                    return Err(RuntimeSignal::static_error_at(
+                        &token,
                         "Invalid assignment target".to_string(),
                     ))
                 }
@@ -393,11 +616,25 @@ impl Parser {
     fn call(&mut self) -> Result<Expr, RuntimeSignal> {
         let mut expr = self.primary()?;
 
-        // suposedly has to be this way for some future feature?
         loop {
             if self.peek().token_type == TokenType::LeftParen {
                 self.advance();
                 expr = self.finish_call(expr)?;
+            } else if self.peek().token_type == TokenType::Dot {
+                self.advance();
+                let name = self.consume(
+                    TokenType::Identifier,
+                    "Expect property name after '.'".into(),
+                )?;
+                expr = Expr::get(self.fresh_expr_id(), expr, name);
+            } else if self.peek().token_type == TokenType::LeftBracket {
+                let bracket = self.advance();
+                let index = self.expression()?;
+                self.consume(
+                    TokenType::RightBracket,
+                    "Expect ']' after index".into(),
+                )?;
+                expr = Expr::index(self.fresh_expr_id(), expr, bracket, index);
             } else {
                 break;
             }
@@ -430,6 +667,13 @@ impl Parser {
     }
 
     fn primary(&mut self) -> Result<Expr, RuntimeSignal> {
+        // 'advance' stays on the last token at the end, so going on would parse it again forever
+        if self.is_at_end() {
+            return Err(RuntimeSignal::static_error_at(
+                self.peek(),
+                "Expect expression before end of input".into(),
+            ));
+        }
         let token = self.advance();
         Ok(match token.token_type {
             TokenType::False => Expr::literal(self.fresh_expr_id(), LiteralValue::Boolean(false)),
@@ -456,9 +700,62 @@ impl Parser {
                 Expr::grouping(self.fresh_expr_id(), expr)
             }
             TokenType::Identifier => Expr::variable(self.fresh_expr_id(), token),
-            t => {
-                return Err(RuntimeSignal::static_error(
+            TokenType::This => Expr::this(self.fresh_expr_id(), token),
+            TokenType::Fun => {
+                let name = Token::new(
+                    TokenType::Identifier,
+                    "lambda".into(),
+                    None,
                     token.line,
+                    token.column,
+                    token.offset,
+                );
+                let fun_def = self.function_definition(name, "lambda")?;
+                Expr::lambda(self.fresh_expr_id(), fun_def)
+            }
+            TokenType::LeftBracket => {
+                let mut elements = Vec::new();
+                if self.peek().token_type != TokenType::RightBracket {
+                    loop {
+                        elements.push(self.expression()?);
+
+                        if self.peek().token_type == TokenType::Comma {
+                            self.advance();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.consume(
+                    TokenType::RightBracket,
+                    "Expect ']' after list elements".into(),
+                )?;
+                Expr::list(self.fresh_expr_id(), token, elements)
+            }
+            TokenType::LeftBrace => {
+                let mut entries = Vec::new();
+                if self.peek().token_type != TokenType::RightBrace {
+                    loop {
+                        let key = self.expression()?;
+                        self.consume(TokenType::Colon, "Expect ':' after map key".into())?;
+                        entries.push((key, self.expression()?));
+
+                        if self.peek().token_type == TokenType::Comma {
+                            self.advance();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.consume(
+                    TokenType::RightBrace,
+                    "Expect '}' after map entries".into(),
+                )?;
+                Expr::map(self.fresh_expr_id(), token, entries)
+            }
+            ref t => {
+                return Err(RuntimeSignal::static_error_at(
+                    &token,
                     format!("unexpected token in primary expression: {t}"),
                 ))
             }
@@ -469,7 +766,7 @@ impl Parser {
         if self.check_current_type(token_type) {
             return Ok(self.advance());
         }
-        Err(RuntimeSignal::static_error(self.peek().line, msg))
+        Err(RuntimeSignal::static_error_at(self.peek(), msg))
     }
 
     fn synchronize(&mut self) {
@@ -516,6 +813,12 @@ impl Parser {
         &self.tokens[self.current]
     }
 
+    fn peek_next_type(&self) -> Option<TokenType> {
+        self.tokens
+            .get(self.current + 1)
+            .map(|token| token.token_type.clone())
+    }
+
     fn previous(&self) -> &Token {
         &self.tokens[self.current - 1]
     }
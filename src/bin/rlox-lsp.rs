@@ -0,0 +1,13 @@
+use std::{io, process};
+
+use rlox::lsp;
+
+// a language server for lox over stdio; point an editor's LSP client at this binary
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let code = lsp::serve(stdin.lock(), stdout.lock()).unwrap_or_else(|err| {
+        panic!("language server connection failed: {:?}", err);
+    });
+    process::exit(code);
+}
@@ -0,0 +1,850 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::expression::{Expr, LiteralValue},
+    compiler::chunk::{CallSite, Chunk, Function, Op, UpvalueDescriptor},
+    error::RuntimeSignal,
+    interpreter::{
+        literal_value,
+        stmt::{
+            ClassDefinition, FunctionDefinition, IfConditions, Stmt, TryStatement, WhileConditions,
+        },
+    },
+    scanner::{token::Token, token_type::TokenType},
+};
+
+pub mod chunk;
+
+// how the top level of a script treats its expression statements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptMode {
+    Run,
+    Echo,  // the REPL prints their values
+    Value, // the script returns the value of a trailing one
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Function,
+    Method,
+    Initializer,
+}
+
+// compiles resolved statements into the function the VM runs as a script
+pub fn compile(statements: &[Stmt], mode: ScriptMode) -> Result<Rc<Function>, RuntimeSignal> {
+    let mut compiler = Compiler {
+        states: vec![FunctionState::new("script".into(), 0, false, "")],
+        mode,
+    };
+    compiler.script(statements)?;
+    let state = compiler
+        .states
+        .pop()
+        .expect("the script state is never popped");
+    Ok(Rc::new(state.finish()))
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    captured: bool, // closed over, so leaving its scope must close the upvalue
+    hidden: bool,   // out of scope where a finally block is copied, but still on the stack
+}
+
+struct Loop {
+    label: Option<String>,
+    depth: usize,
+    tries: usize, // try statements entered before the loop
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// a try statement being compiled; jumps out of it pop its handlers and run its finally block
+#[derive(Clone, Copy)]
+struct TryBlock<'a> {
+    handlers: usize,
+    finally: Option<&'a [Stmt]>,
+    loops: usize,
+    locals: usize,
+}
+
+struct FunctionState<'a> {
+    name: String,
+    arity: usize,
+    is_initializer: bool,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueDescriptor>,
+    depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryBlock<'a>>,
+}
+
+impl FunctionState<'_> {
+    // slot 0 is named 'this' in methods; elsewhere it can't be named
+    fn new(name: String, arity: usize, is_initializer: bool, receiver: &str) -> Self {
+        FunctionState {
+            name,
+            arity,
+            is_initializer,
+            chunk: Chunk::default(),
+            locals: vec![Local {
+                name: receiver.into(),
+                depth: 0,
+                captured: false,
+                hidden: false,
+            }],
+            upvalues: Vec::new(),
+            depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> Function {
+        Function {
+            name: self.name,
+            arity: self.arity,
+            chunk: self.chunk,
+            upvalues: self.upvalues,
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+struct Compiler<'a> {
+    states: Vec<FunctionState<'a>>, // the functions being compiled, innermost last
+    mode: ScriptMode,
+}
+
+impl<'a> Compiler<'a> {
+    fn script(&mut self, statements: &'a [Stmt]) -> Result<(), RuntimeSignal> {
+        match (self.mode, statements.split_last()) {
+            (ScriptMode::Value, Some((Stmt::Expression(last), rest))) => {
+                for stmt in rest {
+                    self.statement(stmt)?;
+                }
+                self.expression(last)?;
+            }
+            _ => {
+                for stmt in statements {
+                    self.statement(stmt)?;
+                }
+                self.emit(Op::Nil);
+            }
+        }
+        self.emit(Op::Return);
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) -> Result<(), RuntimeSignal> {
+        self.emit(Op::Statement);
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.expression(expr)?;
+                if self.mode == ScriptMode::Echo && self.at_top_level() {
+                    self.emit(Op::Echo);
+                } else {
+                    self.emit(Op::Pop);
+                }
+            }
+            Stmt::Print(_, expr) => {
+                self.expression(expr)?;
+                self.emit(Op::Print);
+            }
+            Stmt::Var(name, initializer) => match initializer {
+                Some(expr) => {
+                    self.expression(expr)?;
+                    self.define_variable(name)?;
+                }
+                None if self.at_top_level() => {
+                    let name = self.token(name);
+                    self.emit(Op::DeclareGlobal(name));
+                }
+                None => {
+                    self.emit(Op::Uninitialized);
+                    self.add_local(name)?;
+                }
+            },
+            Stmt::Block(statements) => self.block(statements)?,
+            Stmt::If(conditions) => self.if_statement(conditions)?,
+            Stmt::While(conditions) => self.while_statement(conditions)?,
+            Stmt::Break(_, label) => self.loop_jump(label, true)?,
+            Stmt::Continue(_, label) => self.loop_jump(label, false)?,
+            Stmt::Function(fun_def) => {
+                if self.at_top_level() {
+                    self.function(fun_def, FunctionKind::Function)?;
+                    let name = self.token(&fun_def.name);
+                    self.emit(Op::DefineGlobal(name));
+                } else {
+                    // declared first so the body can refer to itself
+                    self.add_local(&fun_def.name)?;
+                    self.function(fun_def, FunctionKind::Function)?;
+                }
+            }
+            Stmt::Class(class_def) => self.class(class_def)?,
+            Stmt::Return(keyword, value) => self.return_statement(keyword, value)?,
+            Stmt::Throw(keyword, value) => {
+                self.expression(value)?;
+                let keyword = self.token(keyword);
+                self.emit(Op::Throw(keyword));
+            }
+            Stmt::Try(try_stmt) => self.try_statement(try_stmt)?,
+            Stmt::Export(declaration) => {
+                self.statement(declaration)?;
+                let name = declaration
+                    .declared_name()
+                    .expect("the parser only exports declarations");
+                let name = self.token(name);
+                self.emit(Op::Export(name));
+            }
+            Stmt::Import(import) => {
+                let chunk = self.chunk();
+                let index = table_index(chunk.imports.len());
+                chunk.imports.push(import.clone());
+                self.emit(Op::Import(index));
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &'a [Stmt]) -> Result<(), RuntimeSignal> {
+        self.begin_scope();
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+        self.end_scope();
+        Ok(())
+    }
+
+    fn if_statement(&mut self, conditions: &'a IfConditions) -> Result<(), RuntimeSignal> {
+        self.expression(&conditions.condition)?;
+        let else_jump = self.emit(Op::JumpIfFalse(0));
+        self.emit(Op::Pop);
+        self.statement(&conditions.then_branch)?;
+        let end_jump = self.emit(Op::Jump(0));
+
+        self.patch_jump(else_jump);
+        self.emit(Op::Pop);
+        if let Some(else_branch) = &conditions.else_branch {
+            self.statement(else_branch)?;
+        }
+        self.patch_jump(end_jump);
+        Ok(())
+    }
+
+    fn while_statement(&mut self, conditions: &'a WhileConditions) -> Result<(), RuntimeSignal> {
+        let start = self.offset();
+        self.expression(&conditions.condition)?;
+        let exit_jump = self.emit(Op::JumpIfFalse(0));
+        self.emit(Op::Pop);
+
+        let state = self.state();
+        state.loops.push(Loop {
+            label: conditions.label.as_ref().map(|label| label.lexeme.clone()),
+            depth: state.depth,
+            tries: state.tries.len(),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+        self.statement(&conditions.stmt_body)?;
+        let finished = self.state().loops.pop().expect("the loop was pushed above");
+
+        // 'continue' still runs a desugared for loop's increment
+        for jump in finished.continues {
+            self.patch_jump(jump);
+        }
+        if let Some(increment) = &conditions.increment {
+            self.expression(increment)?;
+            self.emit(Op::Pop);
+        }
+        self.emit(Op::Jump(start));
+
+        self.patch_jump(exit_jump);
+        self.emit(Op::Pop);
+        for jump in finished.breaks {
+            self.patch_jump(jump);
+        }
+        Ok(())
+    }
+
+    fn loop_jump(&mut self, label: &Option<Token>, is_break: bool) -> Result<(), RuntimeSignal> {
+        let loops = &self.state().loops;
+        let index = match label {
+            Some(label) => loops
+                .iter()
+                .rposition(|target| target.label.as_deref() == Some(label.lexeme.as_str())),
+            None => loops.len().checked_sub(1),
+        }
+        .expect("the resolver rejects jumps without a target loop");
+        let (depth, tries) = (loops[index].depth, loops[index].tries);
+
+        self.exit_tries(tries)?;
+        self.discard_locals(depth);
+        let jump = self.emit(Op::Jump(0));
+
+        let target = &mut self.state().loops[index];
+        if is_break {
+            target.breaks.push(jump);
+        } else {
+            target.continues.push(jump);
+        }
+        Ok(())
+    }
+
+    fn return_statement(
+        &mut self,
+        keyword: &Token,
+        value: &'a Option<Expr>,
+    ) -> Result<(), RuntimeSignal> {
+        match value {
+            Some(expr) => self.expression(expr)?,
+            None => {
+                self.emit(Op::Nil);
+            }
+        }
+
+        if self.states.len() == 1 {
+            // the value is still evaluated, then the return is reported like the tree-walker does
+            self.emit(Op::Pop);
+            self.exit_tries(0)?;
+            self.emit(Op::EscapedReturn);
+            return Ok(());
+        }
+
+        if self.state().tries.is_empty() {
+            self.emit(Op::Return);
+            return Ok(());
+        }
+
+        // keep the value in a slot of its own while the finally blocks run
+        self.begin_scope();
+        self.add_hidden_local(keyword)?;
+        self.exit_tries(0)?;
+        self.emit(Op::Return);
+        // unreachable, but keeps the compiler's locals in step with the stack
+        self.end_scope();
+        Ok(())
+    }
+
+    // 'finally' runs on every way out: falling off the end, a jump, or an error, which is
+    // caught by an outer catch-all handler and rethrown once the block has run
+    fn try_statement(&mut self, try_stmt: &'a TryStatement) -> Result<(), RuntimeSignal> {
+        let finally_handler = try_stmt
+            .finally
+            .as_ref()
+            .map(|_| self.emit(Op::PushHandler(0, false)));
+        let catch_handler = try_stmt
+            .catch
+            .as_ref()
+            .map(|_| self.emit(Op::PushHandler(0, true)));
+
+        let state = self.state();
+        state.tries.push(TryBlock {
+            handlers: usize::from(finally_handler.is_some()) + usize::from(catch_handler.is_some()),
+            finally: try_stmt.finally.as_deref(),
+            loops: state.loops.len(),
+            locals: state.locals.len(),
+        });
+        self.block(&try_stmt.body)?;
+
+        if let (Some(catch), Some(handler)) = (&try_stmt.catch, catch_handler) {
+            self.emit(Op::PopHandler);
+            if let Some(entry) = self.state().tries.last_mut() {
+                entry.handlers -= 1;
+            }
+            let skip = self.emit(Op::Jump(0));
+
+            // reached with the caught value on the stack
+            self.patch_jump(handler);
+            self.begin_scope();
+            self.add_local(&catch.name)?;
+            self.block(&catch.body)?;
+            self.end_scope();
+            self.patch_jump(skip);
+        }
+        self.state().tries.pop();
+
+        if let (Some(finally), Some(handler)) = (&try_stmt.finally, finally_handler) {
+            self.emit(Op::PopHandler);
+            self.block(finally)?;
+            let end = self.emit(Op::Jump(0));
+
+            // reached with the error on the stack
+            self.patch_jump(handler);
+            self.begin_scope();
+            let slot = self.add_hidden_local(&try_stmt.keyword)?;
+            self.block(finally)?;
+            self.emit(Op::Rethrow(slot));
+            self.end_scope();
+            self.patch_jump(end);
+        }
+        Ok(())
+    }
+
+    // leaves the try statements entered after the first 'floor' ones, innermost first
+    fn exit_tries(&mut self, floor: usize) -> Result<(), RuntimeSignal> {
+        for index in (floor..self.state().tries.len()).rev() {
+            let entry = self.state().tries[index];
+            for _ in 0..entry.handlers {
+                self.emit(Op::PopHandler);
+            }
+            if let Some(finally) = entry.finally {
+                self.inline_finally(index, entry, finally)?;
+            }
+        }
+        Ok(())
+    }
+
+    // compiles a copy of a finally block that sees only what its try statement sees
+    fn inline_finally(
+        &mut self,
+        index: usize,
+        entry: TryBlock<'a>,
+        finally: &'a [Stmt],
+    ) -> Result<(), RuntimeSignal> {
+        let state = self.state();
+        let inner_tries = state.tries.split_off(index);
+        let inner_loops = state.loops.split_off(entry.loops);
+        let hidden: Vec<bool> = state.locals[entry.locals..]
+            .iter()
+            .map(|local| local.hidden)
+            .collect();
+        for local in &mut state.locals[entry.locals..] {
+            local.hidden = true;
+        }
+
+        let result = self.block(finally);
+
+        let state = self.state();
+        for (local, hidden) in state.locals[entry.locals..].iter_mut().zip(hidden) {
+            local.hidden = hidden;
+        }
+        state.loops.extend(inner_loops);
+        state.tries.extend(inner_tries);
+        result
+    }
+
+    fn class(&mut self, class_def: &'a ClassDefinition) -> Result<(), RuntimeSignal> {
+        let is_global = self.at_top_level();
+        if !is_global {
+            // a slot for the class, so methods can capture it
+            self.emit(Op::Nil);
+            self.add_local(&class_def.name)?;
+        }
+
+        for method in &class_def.methods {
+            let kind = if method.name.lexeme == "init" {
+                FunctionKind::Initializer
+            } else {
+                FunctionKind::Method
+            };
+            self.function(method, kind)?;
+        }
+
+        let count = u16::try_from(class_def.methods.len())
+            .map_err(|_| too_many(&class_def.name, "methods in a class"))?;
+        let name = self.token(&class_def.name);
+        self.emit(Op::Class(name, count));
+
+        if is_global {
+            self.emit(Op::DefineGlobal(name));
+        } else {
+            let slot = self.state().locals.len() - 1;
+            self.emit(Op::SetLocal(slot as u16));
+            self.emit(Op::Pop);
+        }
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        fun_def: &'a FunctionDefinition,
+        kind: FunctionKind,
+    ) -> Result<(), RuntimeSignal> {
+        let receiver = if kind == FunctionKind::Function {
+            ""
+        } else {
+            "this"
+        };
+        self.states.push(FunctionState::new(
+            fun_def.name.lexeme.clone(),
+            fun_def.params.len(),
+            kind == FunctionKind::Initializer,
+            receiver,
+        ));
+        let result = self.function_body(fun_def);
+        let state = self
+            .states
+            .pop()
+            .expect("the function state was pushed above");
+        result?;
+
+        let chunk = self.chunk();
+        let index = table_index(chunk.functions.len());
+        chunk.functions.push(Rc::new(state.finish()));
+        self.emit(Op::Closure(index));
+        Ok(())
+    }
+
+    fn function_body(&mut self, fun_def: &'a FunctionDefinition) -> Result<(), RuntimeSignal> {
+        for param in &fun_def.params {
+            self.add_local(param)?;
+        }
+        self.block(&fun_def.body)?;
+        self.emit(Op::Nil);
+        self.emit(Op::Return);
+        Ok(())
+    }
+
+    fn expression(&mut self, expr: &'a Expr) -> Result<(), RuntimeSignal> {
+        match expr {
+            Expr::Assignment { name, value, .. } => {
+                self.expression(value)?;
+                self.set_variable(name)?;
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                self.expression(left)?;
+                if operator.token_type == TokenType::Or {
+                    let else_jump = self.emit(Op::JumpIfFalse(0));
+                    let end_jump = self.emit(Op::Jump(0));
+                    self.patch_jump(else_jump);
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end_jump);
+                } else {
+                    let end_jump = self.emit(Op::JumpIfFalse(0));
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end_jump);
+                }
+            }
+            Expr::Binary {
+                left_expr,
+                operator,
+                right_expr,
+                ..
+            } => {
+                self.expression(left_expr)?;
+                self.expression(right_expr)?;
+                let operator = self.token(operator);
+                self.emit(Op::Binary(operator));
+            }
+            Expr::Unary {
+                token, expression, ..
+            } => {
+                self.expression(expression)?;
+                if token.token_type == TokenType::Bang {
+                    self.emit(Op::Not);
+                } else {
+                    let operator = self.token(token);
+                    self.emit(Op::Negate(operator));
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+                ..
+            } => {
+                self.expression(callee)?;
+                for argument in arguments {
+                    self.expression(argument)?;
+                }
+                let count = u16::try_from(arguments.len())
+                    .map_err(|_| too_many(paren, "arguments in a call"))?;
+                let chunk = self.chunk();
+                let site = table_index(chunk.call_sites.len());
+                chunk.call_sites.push(CallSite {
+                    paren: paren.clone(),
+                    callee: callee.to_string(),
+                });
+                self.emit(Op::Call(count, site));
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.expression(element)?;
+                }
+                self.emit(Op::List(table_index(elements.len())));
+            }
+            Expr::Lambda { fun_def, .. } => self.function(fun_def, FunctionKind::Function)?,
+            Expr::Map { brace, entries, .. } => {
+                let brace = self.token(brace);
+                for (key, value) in entries {
+                    self.expression(key)?;
+                    self.emit(Op::CheckKey(brace));
+                    self.expression(value)?;
+                }
+                self.emit(Op::Map(table_index(entries.len())));
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => {
+                self.expression(object)?;
+                self.expression(index)?;
+                let bracket = self.token(bracket);
+                self.emit(Op::GetIndex(bracket));
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+                ..
+            } => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.expression(value)?;
+                let bracket = self.token(bracket);
+                self.emit(Op::SetIndex(bracket));
+            }
+            Expr::Get { object, name, .. } => {
+                self.expression(object)?;
+                let name = self.token(name);
+                self.emit(Op::GetProperty(name));
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => {
+                self.expression(object)?;
+                let name = self.token(name);
+                self.emit(Op::RequireInstance(name));
+                self.expression(value)?;
+                self.emit(Op::SetProperty(name));
+            }
+            Expr::This { keyword, .. } => self.get_variable(keyword)?,
+            Expr::Grouping { expression, .. } => self.expression(expression)?,
+            Expr::Literal { value, .. } => self.literal(value),
+            Expr::Variable { token, .. } => self.get_variable(token)?,
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &LiteralValue) {
+        let op = match literal {
+            LiteralValue::Nil => Op::Nil,
+            LiteralValue::Boolean(true) => Op::True,
+            LiteralValue::Boolean(false) => Op::False,
+            literal => {
+                let chunk = self.chunk();
+                let index = table_index(chunk.constants.len());
+                chunk.constants.push(literal_value(literal));
+                Op::Constant(index)
+            }
+        };
+        self.emit(op);
+    }
+
+    // ============ variables ============
+
+    fn get_variable(&mut self, name: &Token) -> Result<(), RuntimeSignal> {
+        let token = self.token(name);
+        let current = self.states.len() - 1;
+        let op = if let Some(slot) = self.resolve_local(current, &name.lexeme) {
+            Op::GetLocal(slot, token)
+        } else if let Some(index) = self.resolve_upvalue(current, name)? {
+            Op::GetUpvalue(index, token)
+        } else {
+            Op::GetGlobal(token)
+        };
+        self.emit(op);
+        Ok(())
+    }
+
+    fn set_variable(&mut self, name: &Token) -> Result<(), RuntimeSignal> {
+        let current = self.states.len() - 1;
+        let op = if let Some(slot) = self.resolve_local(current, &name.lexeme) {
+            Op::SetLocal(slot)
+        } else if let Some(index) = self.resolve_upvalue(current, name)? {
+            Op::SetUpvalue(index)
+        } else {
+            Op::SetGlobal(self.token(name))
+        };
+        self.emit(op);
+        Ok(())
+    }
+
+    // the value to bind is on top of the stack
+    fn define_variable(&mut self, name: &Token) -> Result<(), RuntimeSignal> {
+        if self.at_top_level() {
+            let name = self.token(name);
+            self.emit(Op::DefineGlobal(name));
+        } else {
+            self.add_local(name)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_local(&self, state: usize, name: &str) -> Option<u16> {
+        self.states[state]
+            .locals
+            .iter()
+            .rposition(|local| !local.hidden && local.name == name)
+            .map(|slot| slot as u16)
+    }
+
+    fn resolve_upvalue(
+        &mut self,
+        state: usize,
+        name: &Token,
+    ) -> Result<Option<u16>, RuntimeSignal> {
+        if state == 0 {
+            return Ok(None);
+        }
+
+        if let Some(slot) = self.resolve_local(state - 1, &name.lexeme) {
+            self.states[state - 1].locals[slot as usize].captured = true;
+            return self.add_upvalue(state, slot, true, name).map(Some);
+        }
+
+        match self.resolve_upvalue(state - 1, name)? {
+            Some(index) => self.add_upvalue(state, index, false, name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(
+        &mut self,
+        state: usize,
+        index: u16,
+        is_local: bool,
+        name: &Token,
+    ) -> Result<u16, RuntimeSignal> {
+        let upvalue = UpvalueDescriptor { index, is_local };
+        let upvalues = &mut self.states[state].upvalues;
+        if let Some(existing) = upvalues.iter().position(|known| *known == upvalue) {
+            return Ok(existing as u16);
+        }
+
+        let added = u16::try_from(upvalues.len())
+            .map_err(|_| too_many(name, "closure variables in a function"))?;
+        upvalues.push(upvalue);
+        Ok(added)
+    }
+
+    fn add_local(&mut self, name: &Token) -> Result<u16, RuntimeSignal> {
+        self.push_local(name.lexeme.clone(), false)
+            .ok_or_else(|| too_many(name, "local variables in a function"))
+    }
+
+    // a slot the compiler uses for its own bookkeeping; no name resolves to it, so an overflow
+    // is reported at 'at', the statement that needs it
+    fn add_hidden_local(&mut self, at: &Token) -> Result<u16, RuntimeSignal> {
+        self.push_local(String::new(), true)
+            .ok_or_else(|| too_many(at, "local variables in a function"))
+    }
+
+    fn push_local(&mut self, name: String, hidden: bool) -> Option<u16> {
+        let state = self.state();
+        let slot = u16::try_from(state.locals.len()).ok()?;
+        state.locals.push(Local {
+            name,
+            depth: state.depth,
+            captured: false,
+            hidden,
+        });
+        Some(slot)
+    }
+
+    // ============ scopes ============
+
+    fn at_top_level(&self) -> bool {
+        self.states.len() == 1 && self.states[0].depth == 0
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let state = self.state();
+        state.depth -= 1;
+        let depth = state.depth;
+        self.discard_locals(depth);
+
+        let locals = &mut self.state().locals;
+        while locals.last().is_some_and(|local| local.depth > depth) {
+            locals.pop();
+        }
+    }
+
+    // pops the locals declared deeper than 'depth' without forgetting them, as jumps need
+    fn discard_locals(&mut self, depth: usize) {
+        let ops: Vec<Op> = self
+            .state()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| {
+                if local.captured {
+                    Op::CloseUpvalue
+                } else {
+                    Op::Pop
+                }
+            })
+            .collect();
+        for op in ops {
+            self.emit(op);
+        }
+    }
+
+    // ============ emitting ============
+
+    fn state(&mut self) -> &mut FunctionState<'a> {
+        self.states
+            .last_mut()
+            .expect("there is always a function being compiled")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().chunk
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let code = &mut self.chunk().code;
+        code.push(op);
+        code.len() - 1
+    }
+
+    fn offset(&mut self) -> u32 {
+        table_index(self.chunk().code.len())
+    }
+
+    fn token(&mut self, token: &Token) -> u32 {
+        let tokens = &mut self.chunk().tokens;
+        tokens.push(token.clone());
+        table_index(tokens.len() - 1)
+    }
+
+    // points a forward jump emitted earlier at the next instruction
+    fn patch_jump(&mut self, at: usize) {
+        let target = self.offset();
+        let code = &mut self.chunk().code;
+        code[at] = match code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::PushHandler(_, catch) => Op::PushHandler(target, catch),
+            op => unreachable!("{op:?} is not a jump"),
+        };
+    }
+}
+
+fn table_index(index: usize) -> u32 {
+    u32::try_from(index).expect("chunk tables hold fewer than u32::MAX entries")
+}
+
+fn too_many(token: &Token, what: &str) -> RuntimeSignal {
+    RuntimeSignal::static_error_at(token, format!("Too many {what}"))
+}
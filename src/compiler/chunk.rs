@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::{
+    interpreter::{stmt::ImportStatement, values::Value},
+    scanner::token::Token,
+};
+
+// operands index into the chunk's tables: 'u32' token operands point into 'tokens',
+// which errors are reported against; jump targets are absolute instruction offsets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(u32),
+    Nil,
+    True,
+    False,
+    Uninitialized, // a local declared without an initializer
+    Pop,
+    Statement, // marks where the tree-walker would check resource limits
+
+    GetLocal(u16, u32),
+    SetLocal(u16),
+    GetUpvalue(u16, u32),
+    SetUpvalue(u16),
+    GetGlobal(u32),
+    SetGlobal(u32),
+    DefineGlobal(u32),
+    DeclareGlobal(u32),
+
+    GetProperty(u32),
+    RequireInstance(u32), // a field assignment's target, checked before the value is evaluated
+    SetProperty(u32),
+    GetIndex(u32),
+    SetIndex(u32),
+
+    Binary(u32),
+    Negate(u32),
+    Not,
+
+    Print,
+    Echo, // prints a REPL expression statement's value
+
+    Jump(u32),
+    JumpIfFalse(u32), // leaves the condition on the stack
+
+    Call(u16, u32), // argument count, call site
+    Closure(u32),
+    CloseUpvalue,
+    Return,
+    Class(u32, u16), // name, method count
+
+    List(u32),
+    CheckKey(u32),
+    Map(u32), // entry count
+
+    Throw(u32),
+    PushHandler(u32, bool), // target, whether it is a catch clause rather than a finally
+    PopHandler,
+    Rethrow(u16), // re-raises the error a finally handler stored in the given slot
+    EscapedReturn,
+
+    Import(u32),
+    Export(u32),
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<Value>,
+    pub tokens: Vec<Token>,
+    pub functions: Vec<Rc<Function>>,
+    pub call_sites: Vec<CallSite>,
+    pub imports: Vec<ImportStatement>,
+}
+
+// what a call instruction needs to report a failed call like the tree-walker does
+#[derive(Debug)]
+pub struct CallSite {
+    pub paren: Token,
+    pub callee: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpvalueDescriptor {
+    pub index: u16,
+    pub is_local: bool, // a slot of the enclosing function rather than one of its upvalues
+}
+
+// a compiled function body; slot 0 of its frame holds the callee, or 'this' for methods
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub upvalues: Vec<UpvalueDescriptor>,
+    pub is_initializer: bool,
+}
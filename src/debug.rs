@@ -0,0 +1,184 @@
+use std::io::{BufRead, Write};
+
+use crate::interpreter::{
+    debugger::{DebugFrontend, Pause, PauseReason, Resume, Scope},
+    values::Value,
+};
+
+pub mod dap;
+
+const PROMPT: &str = "(debug) ";
+const LIST_CONTEXT: usize = 2; // lines shown either side of the current one
+
+const HELP: &str = "\
+break N | b N     pause whenever line N is reached
+clear N           remove the breakpoint on line N
+breakpoints       list breakpoints
+continue | c      run to the next breakpoint
+step | s          step into the next statement
+next | n          step over calls to the next statement here
+out | o           run until the current function returns
+backtrace | bt    print the call stack
+vars | v          print the variables of every scope
+print NAME | p    print one variable, looked up like the script would
+list | l          show the source around the current line
+quit | q          stop the script
+help | h          show this list";
+
+// a line-oriented debugger front end, for 'rlox debug'
+pub struct Console<R, W> {
+    lines: Vec<String>,
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Console {
+            lines: source.lines().map(str::to_string).collect(),
+            input,
+            output,
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        line.checked_sub(1)
+            .and_then(|index| self.lines.get(index))
+            .map_or("", |text| text.trim_end())
+    }
+
+    // one command; None to resume with the given action
+    fn command(&mut self, command: &str, pause: &mut Pause) -> Option<Resume> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let argument = words.next();
+
+        match name {
+            "c" | "continue" => return Some(Resume::Continue),
+            "s" | "step" => return Some(Resume::StepIn),
+            "n" | "next" => return Some(Resume::StepOver),
+            "o" | "out" => return Some(Resume::StepOut),
+            "q" | "quit" => return Some(Resume::Stop),
+            "b" | "break" | "clear" => match argument.map(str::parse::<usize>) {
+                Some(Ok(line)) if name == "clear" => {
+                    if pause.breakpoints.remove(&line) {
+                        self.say(format!("Breakpoint cleared on line {line}"));
+                    } else {
+                        self.say(format!("No breakpoint on line {line}"));
+                    }
+                }
+                Some(Ok(line)) => {
+                    pause.breakpoints.insert(line);
+                    self.say(format!("Breakpoint set on line {line}"));
+                }
+                _ => self.say(format!("usage: {name} LINE")),
+            },
+            "breakpoints" => {
+                if pause.breakpoints.is_empty() {
+                    self.say("No breakpoints".to_string());
+                }
+                let lines: Vec<usize> = pause.breakpoints.iter().copied().collect();
+                for line in lines {
+                    let text = format!("line {line}: {}", self.source_line(line).trim());
+                    self.say(text);
+                }
+            }
+            "bt" | "backtrace" => {
+                for (index, frame) in pause.frames.iter().enumerate() {
+                    self.say(format!(
+                        "#{index} [line {}] in {}()",
+                        frame.line, frame.function
+                    ));
+                }
+            }
+            "v" | "vars" => {
+                for scope in &pause.scopes {
+                    self.say(format!("{}:", scope.name));
+                    if scope.variables.is_empty() {
+                        self.say("  (none)".to_string());
+                    }
+                    for (name, value) in &scope.variables {
+                        self.say(format!("  {}", describe(name, value)));
+                    }
+                }
+            }
+            "p" | "print" => match argument {
+                Some(wanted) => match find_variable(&pause.scopes, wanted) {
+                    Some((name, value)) => self.say(describe(name, value)),
+                    None => self.say(format!("No variable named '{wanted}' in scope")),
+                },
+                None => self.say(format!("usage: {name} NAME")),
+            },
+            "l" | "list" => {
+                let first = pause.line.saturating_sub(LIST_CONTEXT).max(1);
+                let last = (pause.line + LIST_CONTEXT).min(self.lines.len());
+                for line in first..=last {
+                    let marker = if line == pause.line { ">" } else { " " };
+                    let text = format!("{marker}{line:>4} | {}", self.source_line(line));
+                    self.say(text);
+                }
+            }
+            "h" | "help" => self.say(HELP.to_string()),
+            "" => {}
+            _ => self.say(format!("Unknown command '{name}', try 'help'")),
+        }
+        None
+    }
+
+    fn say(&mut self, text: String) {
+        // a vanished terminal is noticed when the next command can't be read
+        let _ = writeln!(self.output, "{text}");
+    }
+}
+
+impl<R: BufRead, W: Write> DebugFrontend for Console<R, W> {
+    fn paused(&mut self, mut pause: Pause) -> Resume {
+        let why = match pause.reason {
+            PauseReason::Entry => "Paused on entry",
+            PauseReason::Breakpoint => "Breakpoint",
+            PauseReason::Step => "Step",
+        };
+        let text = format!(
+            "{why} at line {}: {}",
+            pause.line,
+            self.source_line(pause.line).trim()
+        );
+        self.say(text);
+
+        loop {
+            let _ = write!(self.output, "{PROMPT}");
+            let _ = self.output.flush();
+
+            let mut command = String::new();
+            match self.input.read_line(&mut command) {
+                // out of commands: let the script finish without pausing again
+                Ok(0) | Err(_) => {
+                    pause.breakpoints.clear();
+                    return Resume::Continue;
+                }
+                Ok(_) => {}
+            }
+            if let Some(resume) = self.command(command.trim(), &mut pause) {
+                return resume;
+            }
+        }
+    }
+}
+
+// the innermost variable with that name, as the script would resolve it
+pub(crate) fn find_variable<'a>(
+    scopes: &'a [Scope],
+    wanted: &str,
+) -> Option<&'a (String, Option<Value>)> {
+    scopes
+        .iter()
+        .flat_map(|scope| scope.variables.iter().rev())
+        .find(|(name, _)| name == wanted)
+}
+
+fn describe(name: &str, value: &Option<Value>) -> String {
+    match value {
+        Some(value) => format!("{name} = {value}"),
+        None => format!("{name} = <uninitialized>"),
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs,
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+use crate::{
+    debug::find_variable,
+    error::SourceFile,
+    interpreter::{
+        debugger::{DebugFrontend, Debugger, Pause, PauseReason, Resume},
+        values::Value,
+        Interpreter,
+    },
+    lsp::{json::Json, read_message, write_message},
+    Lox,
+};
+
+// scripts are single threaded, so there is only ever this one
+const THREAD_ID: usize = 1;
+
+// the client end of a Debug Adapter Protocol session; the adapter and the script's output
+// both send through it
+struct Connection {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: usize,
+    disconnected: bool,
+}
+
+type ConnectionRef = Rc<RefCell<Connection>>;
+
+impl Connection {
+    // the next request, skipping anything that isn't one; None at end of input
+    fn receive(&mut self) -> io::Result<Option<Json>> {
+        while let Some(body) = read_message(&mut self.input)? {
+            if let Ok(message) = Json::parse(&body)
+                && message.get("type").as_str() == Some("request")
+            {
+                return Ok(Some(message));
+            }
+        }
+        Ok(None)
+    }
+
+    fn send(&mut self, kind: &str, fields: Vec<(&str, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        let mut message = vec![
+            ("seq".to_string(), self.seq.into()),
+            ("type".to_string(), kind.into()),
+        ];
+        message.extend(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value)),
+        );
+        write_message(&mut self.output, &Json::Object(message))
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send("event", vec![("event", event.into()), ("body", body)])
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        self.send(
+            "response",
+            vec![
+                ("request_seq", request.get("seq").clone()),
+                ("success", true.into()),
+                ("command", request.get("command").clone()),
+                ("body", body),
+            ],
+        )
+    }
+
+    fn fail(&mut self, request: &Json, message: String) -> io::Result<()> {
+        self.send(
+            "response",
+            vec![
+                ("request_seq", request.get("seq").clone()),
+                ("success", false.into()),
+                ("command", request.get("command").clone()),
+                ("message", message.into()),
+            ],
+        )
+    }
+
+    fn output(&mut self, category: &str, text: String) -> io::Result<()> {
+        self.event(
+            "output",
+            Json::object([("category", category.into()), ("output", text.into())]),
+        )
+    }
+}
+
+// serves one debug session: the client configures it, the program named by 'launch' runs
+// once configuration is done, and the session ends on 'disconnect' or end of input
+pub fn serve(input: impl BufRead + 'static, output: impl Write + 'static) -> io::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection {
+        input: Box::new(input),
+        output: Box::new(output),
+        seq: 0,
+        disconnected: false,
+    }));
+
+    let mut program: Option<String> = None;
+    let mut stop_on_entry = false;
+    let mut configured = false;
+    let mut breakpoints = BTreeSet::new();
+
+    loop {
+        if connection.borrow().disconnected {
+            return Ok(());
+        }
+        let Some(request) = connection.borrow_mut().receive()? else {
+            return Ok(());
+        };
+        let arguments = request.get("arguments");
+
+        let mut client = connection.borrow_mut();
+        match request.get("command").as_str().unwrap_or("") {
+            "initialize" => {
+                client.respond(
+                    &request,
+                    Json::object([("supportsConfigurationDoneRequest", true.into())]),
+                )?;
+                client.event("initialized", Json::object([]))?;
+            }
+            "launch" => {
+                program = arguments.get("program").as_str().map(str::to_string);
+                stop_on_entry = arguments.get("stopOnEntry").as_bool().unwrap_or(false);
+                match program {
+                    Some(_) => client.respond(&request, Json::object([]))?,
+                    None => client.fail(&request, "launch needs a 'program'".into())?,
+                }
+            }
+            "setBreakpoints" => {
+                breakpoints = requested_breakpoints(arguments);
+                client.respond(&request, verified_breakpoints(&breakpoints))?;
+            }
+            "configurationDone" => {
+                configured = true;
+                client.respond(&request, Json::object([]))?;
+            }
+            "threads" => client.respond(&request, threads())?,
+            "disconnect" | "terminate" => {
+                client.respond(&request, Json::object([]))?;
+                return Ok(());
+            }
+            command => client.fail(&request, format!("unsupported request '{command}'"))?,
+        }
+        drop(client);
+
+        // the program starts once it is known and the client has set its breakpoints
+        if configured && let Some(path) = program.take() {
+            launch(&connection, &path, &breakpoints, stop_on_entry)?;
+        }
+    }
+}
+
+fn launch(
+    connection: &ConnectionRef,
+    path: &str,
+    breakpoints: &BTreeSet<usize>,
+    stop_on_entry: bool,
+) -> io::Result<()> {
+    match fs::read_to_string(path) {
+        Ok(source) => run(connection, path, &source, breakpoints, stop_on_entry)?,
+        Err(err) => connection
+            .borrow_mut()
+            .output("stderr", format!("failed to read {path}: {err}\n"))?,
+    }
+    connection
+        .borrow_mut()
+        .event("terminated", Json::object([]))
+}
+
+fn run(
+    connection: &ConnectionRef,
+    path: &str,
+    source: &str,
+    breakpoints: &BTreeSet<usize>,
+    stop_on_entry: bool,
+) -> io::Result<()> {
+    let source_file = SourceFile::new(path, source);
+    let stdout = OutputEvents::new(connection, "stdout");
+    let stderr = OutputEvents::new(connection, "stderr");
+    let mut lox =
+        Lox::with_interpreter(Interpreter::with_output(Box::new(stdout), Box::new(stderr)));
+
+    let statements = match lox.compile(source) {
+        Ok(statements) => statements,
+        Err(errors) => {
+            for error in errors {
+                let text = format!("{}\n", source_file.render(&error));
+                connection.borrow_mut().output("stderr", text)?;
+            }
+            return Ok(());
+        }
+    };
+
+    let mut debugger = Debugger::new(Adapter {
+        connection: connection.clone(),
+        path: path.to_string(),
+    });
+    breakpoints
+        .iter()
+        .for_each(|line| debugger.set_breakpoint(*line));
+    if !stop_on_entry {
+        debugger.skip_entry();
+    }
+
+    let interpreter = lox.interpreter();
+    interpreter.set_source(source_file);
+    interpreter.set_script_path(path);
+    interpreter.set_debugger(debugger);
+    interpreter.interpret(&statements);
+    Ok(())
+}
+
+// answers the client's questions while the script is paused
+struct Adapter {
+    connection: ConnectionRef,
+    path: String,
+}
+
+impl DebugFrontend for Adapter {
+    fn paused(&mut self, mut pause: Pause) -> Resume {
+        // with the connection gone there is nobody left to resume the script
+        self.serve_pause(&mut pause).unwrap_or(Resume::Stop)
+    }
+}
+
+impl Adapter {
+    fn serve_pause(&mut self, pause: &mut Pause) -> io::Result<Resume> {
+        let mut connection = self.connection.borrow_mut();
+        let reason = match pause.reason {
+            PauseReason::Entry => "entry",
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+        };
+        connection.event(
+            "stopped",
+            Json::object([
+                ("reason", reason.into()),
+                ("threadId", THREAD_ID.into()),
+                ("allThreadsStopped", true.into()),
+            ]),
+        )?;
+
+        loop {
+            let Some(request) = connection.receive()? else {
+                return Ok(Resume::Stop);
+            };
+            let arguments = request.get("arguments");
+            let command = request.get("command").as_str().unwrap_or("");
+
+            let resume = match command {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                "disconnect" | "terminate" => Some(Resume::Stop),
+                _ => None,
+            };
+            if let Some(resume) = resume {
+                let body = match command {
+                    "continue" => Json::object([("allThreadsContinued", true.into())]),
+                    _ => Json::object([]),
+                };
+                connection.respond(&request, body)?;
+                connection.disconnected |= command == "disconnect";
+                return Ok(resume);
+            }
+
+            match command {
+                "threads" => connection.respond(&request, threads())?,
+                "stackTrace" => {
+                    let frames = pause
+                        .frames
+                        .iter()
+                        .enumerate()
+                        .map(|(id, frame)| {
+                            Json::object([
+                                ("id", id.into()),
+                                ("name", frame.function.as_str().into()),
+                                ("line", frame.line.into()),
+                                ("column", 1.into()),
+                                (
+                                    "source",
+                                    Json::object([("path", self.path.as_str().into())]),
+                                ),
+                            ])
+                        })
+                        .collect();
+                    connection.respond(
+                        &request,
+                        Json::object([
+                            ("stackFrames", Json::Array(frames)),
+                            ("totalFrames", pause.frames.len().into()),
+                        ]),
+                    )?;
+                }
+                // only the innermost frame's environments are still reachable; references
+                // are indices into its scopes, offset by one as 0 means 'none'
+                "scopes" => {
+                    let scopes = match arguments.get("frameId").as_usize() {
+                        Some(0) => pause
+                            .scopes
+                            .iter()
+                            .enumerate()
+                            .map(|(index, scope)| {
+                                Json::object([
+                                    ("name", scope.name.as_str().into()),
+                                    ("variablesReference", (index + 1).into()),
+                                    ("expensive", false.into()),
+                                ])
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    connection
+                        .respond(&request, Json::object([("scopes", Json::Array(scopes))]))?;
+                }
+                "variables" => {
+                    let scope = arguments
+                        .get("variablesReference")
+                        .as_usize()
+                        .and_then(|reference| reference.checked_sub(1))
+                        .and_then(|index| pause.scopes.get(index));
+                    let variables = scope
+                        .map(|scope| {
+                            scope
+                                .variables
+                                .iter()
+                                .map(|(name, value)| variable(name, value))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    connection.respond(
+                        &request,
+                        Json::object([("variables", Json::Array(variables))]),
+                    )?;
+                }
+                // hovering or watching a plain variable name
+                "evaluate" => {
+                    let expression = arguments.get("expression").as_str().unwrap_or("").trim();
+                    match find_variable(&pause.scopes, expression) {
+                        Some((_, value)) => connection.respond(
+                            &request,
+                            Json::object([
+                                ("result", display(value).into()),
+                                ("variablesReference", 0.into()),
+                            ]),
+                        )?,
+                        None => connection
+                            .fail(&request, format!("no variable named '{expression}'"))?,
+                    }
+                }
+                "setBreakpoints" => {
+                    *pause.breakpoints = requested_breakpoints(arguments);
+                    connection.respond(&request, verified_breakpoints(pause.breakpoints))?;
+                }
+                _ => connection.fail(&request, format!("unsupported request '{command}'"))?,
+            }
+        }
+    }
+}
+
+// the script's output, sent as 'output' events a line at a time
+struct OutputEvents {
+    connection: ConnectionRef,
+    category: &'static str,
+    pending: Vec<u8>,
+}
+
+impl OutputEvents {
+    fn new(connection: &ConnectionRef, category: &'static str) -> Self {
+        OutputEvents {
+            connection: connection.clone(),
+            category,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if self.pending.ends_with(b"\n") {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        self.connection.borrow_mut().output(self.category, text)
+    }
+}
+
+fn threads() -> Json {
+    Json::object([(
+        "threads",
+        Json::Array(vec![Json::object([
+            ("id", THREAD_ID.into()),
+            ("name", "main".into()),
+        ])]),
+    )])
+}
+
+// breakpoints are per file, and only the launched program has any
+fn requested_breakpoints(arguments: &Json) -> BTreeSet<usize> {
+    arguments
+        .get("breakpoints")
+        .as_array()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|breakpoint| breakpoint.get("line").as_usize())
+        .collect()
+}
+
+fn verified_breakpoints(lines: &BTreeSet<usize>) -> Json {
+    let breakpoints = lines
+        .iter()
+        .map(|line| Json::object([("verified", true.into()), ("line", (*line).into())]))
+        .collect();
+    Json::object([("breakpoints", Json::Array(breakpoints))])
+}
+
+fn variable(name: &str, value: &Option<Value>) -> Json {
+    Json::object([
+        ("name", name.into()),
+        ("value", display(value).into()),
+        ("variablesReference", 0.into()),
+    ])
+}
+
+fn display(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "<uninitialized>".to_string(), ToString::to_string)
+}
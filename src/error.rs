@@ -1,56 +1,360 @@
 use std::fmt::Display;
 
-use crate::{interpreter::values::Value, scanner::token::Token};
+use crate::{interpreter::values::Value, lint::Warning, scanner::token::Token};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ErrorKind {
     Static,
     Runtime,
+    // resource limits set by the host; unlike runtime errors, scripts can't catch these
+    BudgetExhausted,
+    DeadlineExceeded,
+    Interrupted,
+}
+
+impl ErrorKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorKind::Static => "Static Error",
+            ErrorKind::Runtime => "Runtime Error",
+            ErrorKind::BudgetExhausted => "Budget Exhausted",
+            ErrorKind::DeadlineExceeded => "Deadline Exceeded",
+            ErrorKind::Interrupted => "Interrupted",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum RuntimeSignal {
     Error(LoxError),
     Return(Option<Value>),
+    Break(Option<String>), // target loop label, None for the innermost loop
+    Continue(Option<String>),
 }
 
 #[derive(Debug, Clone)]
 pub struct LoxError {
     line: usize,
+    column: Option<usize>,
+    offset: Option<usize>, // byte offset of the token into the source
+    length: usize,
     message: String,
     kind: ErrorKind,
+    trace: Vec<CallFrame>,
+    thrown: Option<Value>, // the operand of a 'throw' statement
+}
+
+const TRACE_REPEATS_SHOWN: usize = 3;
+
+// one active lox call: the callee's name and the line it was called from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    pub function: String,
+    pub line: usize,
+}
+
+impl LoxError {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    // lets editors and other tools locate the error without counting lines and columns
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    // outermost call first; empty for errors raised at the top level
+    pub fn trace(&self) -> &[CallFrame] {
+        &self.trace
+    }
+
+    // gives errors raised without a position (e.g. by natives) the location of the call
+    pub fn locate_at(&mut self, token: &Token) {
+        if self.line == 0 {
+            self.line = token.line;
+            self.column = Some(token.column);
+            self.offset = Some(token.offset);
+            self.length = token.length;
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: Vec<CallFrame>) {
+        self.trace = trace;
+    }
+
+    // the value passed to 'throw', None for errors raised by the interpreter itself
+    pub fn thrown(&self) -> Option<&Value> {
+        self.thrown.as_ref()
+    }
+
+    fn write_trace(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        if self.trace.is_empty() {
+            return Ok(());
+        }
+
+        write!(f, "\nTraceback (most recent call last):")?;
+        // runaway recursion repeats one frame, so long runs are shortened
+        for run in self.trace.chunk_by(|a, b| a == b) {
+            for frame in run.iter().take(TRACE_REPEATS_SHOWN) {
+                write!(f, "\n  [line {}] in {}()", frame.line, frame.function)?;
+            }
+            if let Some(hidden) = run.len().checked_sub(TRACE_REPEATS_SHOWN)
+                && hidden > 0
+            {
+                write!(f, "\n  [Previous line repeated {hidden} more times]")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl RuntimeSignal {
+    // control flow that escapes to the top level is reported as an error
+    pub fn into_error(self) -> LoxError {
+        match self {
+            RuntimeSignal::Error(err) => err,
+            RuntimeSignal::Return(_) => {
+                Self::escaped_control_flow("Should not be returning from top level")
+            }
+            RuntimeSignal::Break(_) | RuntimeSignal::Continue(_) => {
+                Self::escaped_control_flow("Should not be breaking out of a loop at top level")
+            }
+        }
+    }
+
+    fn escaped_control_flow(message: &str) -> LoxError {
+        LoxError {
+            line: 0,
+            column: None,
+            offset: None,
+            length: 0,
+            message: message.into(),
+            kind: ErrorKind::Static,
+            trace: Vec::new(),
+            thrown: None,
+        }
+    }
+
     pub fn static_error(line: usize, message: String) -> Self {
         RuntimeSignal::Error(LoxError {
             line,
+            column: None,
+            offset: None,
+            length: 0,
+            message,
+            kind: ErrorKind::Static,
+            trace: Vec::new(),
+            thrown: None,
+        })
+    }
+
+    pub fn static_error_at(token: &Token, message: String) -> Self {
+        Self::static_error_span(
+            token.line,
+            token.column,
+            token.offset,
+            token.length,
+            message,
+        )
+    }
+
+    // for source that isn't a token yet, e.g. in the scanner; 'offset' is in bytes
+    pub fn static_error_span(
+        line: usize,
+        column: usize,
+        offset: usize,
+        length: usize,
+        message: String,
+    ) -> Self {
+        RuntimeSignal::Error(LoxError {
+            line,
+            column: Some(column),
+            offset: Some(offset),
+            length,
             message,
             kind: ErrorKind::Static,
+            trace: Vec::new(),
+            thrown: None,
+        })
+    }
+
+    // for natives, which have no token; the interpreter fills in the call site
+    pub fn native_error(message: String) -> Self {
+        RuntimeSignal::Error(LoxError {
+            line: 0,
+            column: None,
+            offset: None,
+            length: 0,
+            message,
+            kind: ErrorKind::Runtime,
+            trace: Vec::new(),
+            thrown: None,
+        })
+    }
+
+    // raised between statements, so like native errors they are located at the innermost call
+    pub fn limit_error(kind: ErrorKind, message: String) -> Self {
+        RuntimeSignal::Error(LoxError {
+            line: 0,
+            column: None,
+            offset: None,
+            length: 0,
+            message,
+            kind,
+            trace: Vec::new(),
+            thrown: None,
         })
     }
 
     pub fn runtime_error(token: Token, message: String) -> Self {
         RuntimeSignal::Error(LoxError {
             line: token.line,
+            column: Some(token.column),
+            offset: Some(token.offset),
+            length: token.length,
             message,
             kind: ErrorKind::Runtime,
+            trace: Vec::new(),
+            thrown: None,
+        })
+    }
+
+    pub fn thrown(token: &Token, value: Value) -> Self {
+        RuntimeSignal::Error(LoxError {
+            line: token.line,
+            column: Some(token.column),
+            offset: Some(token.offset),
+            length: token.length,
+            message: format!("Uncaught {value}"),
+            kind: ErrorKind::Runtime,
+            trace: Vec::new(),
+            thrown: Some(value),
         })
     }
 }
 
+// a named source text that errors can be rendered against
+pub struct SourceFile {
+    name: String,
+    text: String,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        SourceFile {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+
+    // rustc style: header, file location, the offending source line and a caret underline
+    pub fn render(&self, err: &LoxError) -> String {
+        let title = err.kind.title();
+        if err.line == 0 {
+            return format!("{title}: {}", err.message);
+        }
+        let mut out = self.snippet(title, &err.message, err.line, err.column, err.length);
+        let _ = err.write_trace(&mut out);
+        out
+    }
+
+    pub fn render_warning(&self, warning: &Warning) -> String {
+        let message = format!("{} [{}]", warning.message, warning.lint.name());
+        self.snippet(
+            "Warning",
+            &message,
+            warning.line,
+            Some(warning.column),
+            warning.length,
+        )
+    }
+
+    fn snippet(
+        &self,
+        title: &str,
+        message: &str,
+        line: usize,
+        column: Option<usize>,
+        length: usize,
+    ) -> String {
+        let Some(source_line) = self.text.lines().nth(line.saturating_sub(1)) else {
+            return format!("{title}: {message}\n --> {}:{line}", self.name);
+        };
+
+        let gutter = " ".repeat(line.to_string().len());
+        let mut out = match column {
+            Some(column) => format!(
+                "{title}: {message}\n{gutter}--> {}:{line}:{column}\n",
+                self.name
+            ),
+            None => format!("{title}: {message}\n{gutter}--> {}:{line}\n", self.name),
+        };
+        out.push_str(&format!("{gutter} |\n{line} | {source_line}\n"));
+
+        if let Some(column) = column {
+            // keep tabs so the caret lines up with the echoed source line
+            let padding: String = source_line
+                .bytes()
+                .take(column.saturating_sub(1))
+                .map(|b| if b == b'\t' { '\t' } else { ' ' })
+                .collect();
+            // tokens like multi-line strings are only underlined up to the end of their first line
+            let visible = source_line.len().saturating_sub(padding.len());
+            let underline = "^".repeat(length.min(visible).max(1));
+            out.push_str(&format!("{gutter} | {padding}{underline}\n"));
+        }
+        out
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ErrorKind::Static => {
+                write!(f, "Static Error on [line {}]: {}", self.line, self.message)
+            }
+            ErrorKind::Runtime => {
+                write!(f, "Runtime Error on [line {}:] {}", self.line, self.message)?;
+                self.write_trace(f)
+            }
+            // limits hit outside any call have no location
+            kind => {
+                write!(f, "{}", kind.title())?;
+                if self.line > 0 {
+                    write!(f, " on [line {}]", self.line)?;
+                }
+                write!(f, ": {}", self.message)?;
+                self.write_trace(f)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoxError {}
+
 impl Display for RuntimeSignal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RuntimeSignal::Error(err) => match err.kind {
-                ErrorKind::Static => {
-                    write!(f, "Static Error on [line {}]: {}", err.line, err.message)
-                }
-                ErrorKind::Runtime => {
-                    write!(f, "Runtime Error on [line {}:] {}", err.line, err.message)
-                }
-            },
+            RuntimeSignal::Error(err) => write!(f, "{err}"),
             RuntimeSignal::Return(val) => write!(f, "Return value: {:#?}", val),
+            RuntimeSignal::Break(label) => write!(f, "Break: {:?}", label),
+            RuntimeSignal::Continue(label) => write!(f, "Continue: {:?}", label),
         }
     }
 }
@@ -0,0 +1,348 @@
+use crate::{
+    ast::parser::Parser,
+    error::{LoxError, RuntimeSignal},
+    lox::into_errors,
+    scanner::{
+        token::{Token, Trivia, TriviaKind},
+        token_type::TokenType,
+        Scanner,
+    },
+};
+
+const INDENT: &str = "  ";
+
+// reprints a program with consistent indentation, spacing and brace placement, keeping its
+// comments and (collapsed) blank lines; only whitespace between tokens ever changes, so the
+// result parses to the same program
+pub fn format_source(source: &str) -> Result<String, Vec<LoxError>> {
+    let mut scanner = Scanner::with_trivia(source.to_string());
+    let (tokens, scan_errors) = scanner.scan_tokens();
+    if !scan_errors.is_empty() {
+        return Err(into_errors(scan_errors));
+    }
+    let (_, parse_errors) = Parser::new(tokens.clone()).parse();
+    if !parse_errors.is_empty() {
+        return Err(into_errors(parse_errors));
+    }
+
+    let mut items: Vec<Item> = tokens
+        .iter()
+        .filter(|token| token.token_type != TokenType::EOF)
+        .map(Item::Token)
+        .chain(scanner.trivia().iter().map(Item::Trivia))
+        .collect();
+    items.sort_by_key(Item::offset);
+
+    let mut printer = Printer::default();
+    for (index, item) in items.iter().enumerate() {
+        match item {
+            Item::Token(token) => printer.token(token),
+            Item::Trivia(trivia) => {
+                let next_line = items.get(index + 1).map(Item::line);
+                printer.trivia(trivia, next_line);
+            }
+        }
+    }
+    let formatted = printer.finish();
+
+    // a safety net: a layout bug must never be allowed to change what the program means
+    let (reformatted, _) = Scanner::new(formatted.clone()).scan_tokens();
+    let changed = tokens
+        .iter()
+        .zip(&reformatted)
+        .find(|(before, after)| {
+            before.token_type != after.token_type || before.lexeme != after.lexeme
+        })
+        .map(|(before, _)| before)
+        // e.g. a comment that swallows the rest of a line, leaving fewer tokens to compare
+        .or_else(|| {
+            (tokens.len() != reformatted.len())
+                .then(|| &tokens[tokens.len().min(reformatted.len()).saturating_sub(1)])
+        });
+    if let Some(token) = changed {
+        return Err(vec![RuntimeSignal::static_error_at(
+            token,
+            "formatting would change the program here".into(),
+        )
+        .into_error()]);
+    }
+    Ok(formatted)
+}
+
+enum Item<'a> {
+    Token(&'a Token),
+    Trivia(&'a Trivia),
+}
+
+impl Item<'_> {
+    fn offset(&self) -> usize {
+        match self {
+            Item::Token(token) => token.offset,
+            Item::Trivia(trivia) => trivia.offset,
+        }
+    }
+
+    fn line(&self) -> usize {
+        match self {
+            Item::Token(token) => token.line,
+            Item::Trivia(trivia) => trivia.line,
+        }
+    }
+}
+
+// what is open around the current token
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frame {
+    Block,  // statements, one per line
+    Header, // the parentheses after 'if', 'while', 'for' or 'catch'
+    Inline, // any other parentheses, a list or a map
+}
+
+// what has to happen before the next item is printed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Break {
+    #[default]
+    None,
+    // after a block's '}': 'else', ';', ')' and the like stay on its line
+    Soft,
+    Hard,
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    line: String, // the line being built, without its indentation
+    indent: usize,
+    frames: Vec<Frame>,
+    pending: Break,
+    blank: bool,           // a blank line was skipped since the last item
+    block_start: bool,     // nothing printed yet in the block just opened
+    statement_start: bool, // the next token begins a statement
+    prev: Option<TokenType>,
+    prev_opener: bool, // the last token was '(', '[' or a map's '{'
+    prev_unary: bool,
+    prev_comment: bool,
+    last_line: usize, // the source line the last printed item ends on
+}
+
+impl Printer {
+    fn token(&mut self, token: &Token) {
+        let kind = &token.token_type;
+        let opens_block = *kind == TokenType::LeftBrace
+            && (self.prev.is_none()
+                || self.statement_start
+                || matches!(
+                    self.prev,
+                    Some(
+                        TokenType::RightParen
+                            | TokenType::Identifier
+                            | TokenType::Try
+                            | TokenType::Finally
+                    )
+                ));
+        let closes_block =
+            *kind == TokenType::RightBrace && self.frames.last() == Some(&Frame::Block);
+        let unary = matches!(kind, TokenType::Bang | TokenType::Minus) && self.expects_operand();
+
+        // closers leave their frame first, so their line is indented like the opener's
+        let closed = if matches!(
+            kind,
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace
+        ) {
+            self.frames.pop()
+        } else {
+            None
+        };
+
+        let joins_soft_break = matches!(
+            kind,
+            TokenType::Else
+                | TokenType::Catch
+                | TokenType::Finally
+                | TokenType::Semicolon
+                | TokenType::Comma
+                | TokenType::Dot
+                | TokenType::LeftParen
+                | TokenType::RightParen
+                | TokenType::RightBracket
+        );
+        let empty_block = closes_block && self.block_start;
+        let new_line = match self.pending {
+            _ if empty_block => false,
+            _ if closes_block => true,
+            Break::Hard => true,
+            Break::Soft => !joins_soft_break,
+            Break::None => false,
+        };
+
+        if new_line {
+            self.start_line(closed.is_some());
+        } else if !empty_block && self.space_before(kind) {
+            self.line.push(' ');
+        }
+        self.line.push_str(&token.lexeme);
+
+        match kind {
+            TokenType::LeftParen => {
+                let header = matches!(
+                    self.prev,
+                    Some(TokenType::If | TokenType::While | TokenType::For | TokenType::Catch)
+                );
+                self.frames
+                    .push(if header { Frame::Header } else { Frame::Inline });
+            }
+            TokenType::LeftBracket => self.frames.push(Frame::Inline),
+            TokenType::LeftBrace if opens_block => self.frames.push(Frame::Block),
+            TokenType::LeftBrace => self.frames.push(Frame::Inline),
+            _ => {}
+        }
+        let ends_statement = *kind == TokenType::Semicolon && self.in_block();
+
+        self.pending = if opens_block || ends_statement {
+            Break::Hard
+        } else if closes_block {
+            Break::Soft
+        } else {
+            Break::None
+        };
+        self.statement_start = opens_block
+            || closes_block
+            || ends_statement
+            || *kind == TokenType::Else
+            || closed == Some(Frame::Header);
+        self.block_start = opens_block;
+        self.blank = false;
+        self.prev_opener = matches!(kind, TokenType::LeftParen | TokenType::LeftBracket)
+            || (*kind == TokenType::LeftBrace && !opens_block);
+        self.prev_unary = unary;
+        self.prev_comment = false;
+        self.prev = Some(kind.clone());
+        self.last_line = token.line + token.lexeme.matches('\n').count();
+    }
+
+    // 'next_line' is where the item after this one starts, if there is one
+    fn trivia(&mut self, trivia: &Trivia, next_line: Option<usize>) {
+        if trivia.kind == TriviaKind::BlankLine {
+            self.blank = true;
+            return;
+        }
+
+        // a comment after code on the same line stays there
+        let trailing = !self.line.is_empty() && trivia.line == self.last_line;
+        if trailing {
+            self.line.push(' ');
+        } else {
+            self.start_line(false);
+        }
+        self.line.push_str(&trivia.text);
+
+        let end_line = trivia.line + trivia.text.matches('\n').count();
+        if trivia.kind == TriviaKind::LineComment {
+            self.pending = Break::Hard;
+        } else if !trailing {
+            // code right after a comment on its own line may share that line
+            self.pending = if next_line == Some(end_line) {
+                Break::None
+            } else {
+                Break::Hard
+            };
+        }
+        self.block_start = false;
+        self.blank = false;
+        self.prev_comment = true;
+        self.last_line = end_line;
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_line();
+        self.out
+    }
+
+    fn start_line(&mut self, closer: bool) {
+        self.flush_line();
+        // blank lines are collapsed to one, and dropped at the edges of blocks and the file
+        if self.blank && !self.block_start && !closer && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        let blocks = self
+            .frames
+            .iter()
+            .filter(|frame| **frame == Frame::Block)
+            .count();
+        let continuation = !closer
+            && self
+                .frames
+                .last()
+                .is_some_and(|frame| *frame != Frame::Block);
+        self.indent = blocks + usize::from(continuation);
+        self.pending = Break::None;
+    }
+
+    fn flush_line(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(&self.line);
+        self.out.push('\n');
+        self.line.clear();
+    }
+
+    fn in_block(&self) -> bool {
+        self.frames
+            .last()
+            .is_none_or(|frame| *frame == Frame::Block)
+    }
+
+    fn expects_operand(&self) -> bool {
+        self.statement_start || !self.prev.as_ref().is_some_and(ends_operand)
+    }
+
+    fn space_before(&self, kind: &TokenType) -> bool {
+        if self.line.is_empty() {
+            return false;
+        }
+        if self.prev_comment {
+            return true;
+        }
+        if matches!(
+            kind,
+            TokenType::RightParen
+                | TokenType::RightBracket
+                | TokenType::Comma
+                | TokenType::Semicolon
+                | TokenType::Dot
+                | TokenType::Colon
+                | TokenType::RightBrace
+        ) {
+            return false;
+        }
+        if self.prev_opener || self.prev_unary || self.prev == Some(TokenType::Dot) {
+            return false;
+        }
+        // calls and indexing hug their callee; after keywords and operators they don't
+        if matches!(kind, TokenType::LeftParen | TokenType::LeftBracket) {
+            return self.statement_start || !self.prev.as_ref().is_some_and(ends_operand);
+        }
+        true
+    }
+}
+
+fn ends_operand(kind: &TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::Identifier
+            | TokenType::Number
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::RightBrace
+    )
+}
@@ -1,28 +1,52 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
+    fmt,
+    io::{self, Write},
+    path::PathBuf,
     rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
     ast::expression::{Expr, LiteralValue},
-    error::RuntimeSignal,
+    compiler::ScriptMode,
+    error::{CallFrame, ErrorKind, LoxError, RuntimeSignal, SourceFile},
     interpreter::{
-        callable::LoxCallable,
-        environment::{EnvRef, Environment},
-        stmt::Stmt,
+        callable::{Arity, LoxCallable},
+        class::{LoxClass, LoxInstance},
+        debugger::Debugger,
+        environment::{EnvRef, Environment, Globals, GlobalsRef},
+        heap::{Heap, HeapRef, HeapStats},
+        limits::Limits,
+        map::{HashKey, LoxMap},
+        module::Modules,
+        stmt::{Stmt, TryStatement},
         values::Value,
+        vm::Vm,
     },
     scanner::{token::Token, token_type::TokenType},
 };
 
-mod callable;
+pub mod callable;
+mod class;
+pub mod debugger;
 mod environment;
+pub mod heap;
+mod limits;
+pub mod map;
+mod module;
+mod natives;
+mod stack;
 pub mod stmt;
 pub mod values;
+mod vm;
+
+pub use stack::spawn_with_stack;
 
 // native function(s)
-fn clock(_args: Vec<Value>) -> Result<Value, RuntimeSignal> {
+fn clock(_interpreter: &mut Interpreter, _args: &[Value]) -> Result<Value, RuntimeSignal> {
     let now = SystemTime::now();
     let since_epoch = now
         .duration_since(UNIX_EPOCH)
@@ -31,78 +55,291 @@ fn clock(_args: Vec<Value>) -> Result<Value, RuntimeSignal> {
     Ok(Value::Number(since_epoch.as_secs_f64()))
 }
 
+// deep enough for ordinary recursion, given a thread with room for it (see 'spawn_with_stack');
+// on a smaller one, calls fail with the same "stack overflow" once half its stack is used
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+// which engine runs resolved statements; both behave the same
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    #[default]
+    TreeWalk,
+    Bytecode,
+}
+
 pub struct Interpreter {
-    pub globals: EnvRef,
-    locals: HashMap<u32, usize>,
+    pub globals: GlobalsRef,
+    locals: HashMap<u32, (usize, usize)>, // expr id -> (scope depth, slot)
     environment: EnvRef,
+    source: Option<SourceFile>,
+    call_stack: Vec<CallFrame>,
+    max_call_depth: usize,
+    limits: Limits,
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    next_expr_id: u32,
+    modules: Modules,
+    backend: Backend,
+    vm: Vm,
+    heap: HeapRef,
+    debugger: Option<Debugger>,
+    stack_base: Option<usize>, // address of the stack where the running script started
 }
 
-pub fn create_global_env() -> EnvRef {
-    let global = Environment::new_env_ref(None);
+pub fn create_global_env() -> GlobalsRef {
+    let global = Globals::new_ref();
 
-    let clock_value = Value::Callable(Rc::new(LoxCallable::Native {
-        arity: 0,
-        function: clock,
-    }));
+    let clock_value = native_value("clock", Arity::Exact(0), clock);
     global.borrow_mut().define("clock".into(), clock_value);
 
     global
 }
 
+fn native_value(
+    name: &str,
+    arity: Arity,
+    function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeSignal> + 'static,
+) -> Value {
+    Value::Callable(Rc::new(LoxCallable::Native {
+        name: name.to_string(),
+        arity,
+        function: Rc::new(function),
+    }))
+}
+
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()), Box::new(io::stderr()))
+    }
+
+    // 'stdout' receives print output and REPL echoes, 'stderr' receives error reports
+    pub fn with_output(stdout: Box<dyn Write>, stderr: Box<dyn Write>) -> Self {
         let global = create_global_env();
-        Interpreter {
+        let heap = Heap::new_ref();
+        heap.borrow_mut().track_globals(&global);
+        let mut interpreter = Interpreter {
             globals: global.clone(),
             locals: HashMap::new(),
-            environment: global,
-        }
+            environment: Environment::top_level(global, heap.clone()),
+            source: None,
+            call_stack: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            limits: Limits::default(),
+            stdout,
+            stderr,
+            next_expr_id: 0,
+            modules: Modules::default(),
+            backend: Backend::default(),
+            vm: Vm::default(),
+            heap,
+            debugger: None,
+            stack_base: None,
+        };
+        natives::define_list_natives(&mut interpreter);
+        natives::define_map_natives(&mut interpreter);
+        interpreter
+    }
+
+    // the source runtime errors are rendered against; without one they print as a single line
+    pub fn set_source(&mut self, source: SourceFile) {
+        self.source = Some(source);
+    }
+
+    // registers a host function as a global; closures can capture whatever host state they need
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeSignal> + 'static,
+    ) {
+        let value = native_value(name, arity.into(), function);
+        self.globals.borrow_mut().define(name.to_string(), value);
+    }
+
+    // the file being run; imports are resolved relative to its directory
+    pub fn set_script_path(&mut self, path: impl Into<PathBuf>) {
+        self.modules.set_current(path.into());
+    }
+
+    // parsers must start numbering here so ids never collide with already resolved code
+    pub fn next_expr_id(&self) -> u32 {
+        self.next_expr_id
+    }
+
+    pub fn reserve_expr_ids(&mut self, next: u32) {
+        self.next_expr_id = self.next_expr_id.max(next);
+    }
+
+    pub fn resolve(&mut self, expr: &Expr, depth: usize, slot: usize) {
+        self.locals.insert(expr.id(), (depth, slot));
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
     }
 
-    pub fn resolve(&mut self, expr: &Expr, depth: usize) {
-        self.locals.insert(expr.id(), depth);
+    // calls nested deeper than this raise a catchable "stack overflow" runtime error
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    // caps the statements one run may execute; None (the default) runs without a budget
+    pub fn set_statement_budget(&mut self, budget: Option<u64>) {
+        self.limits.set_statement_budget(budget);
+    }
+
+    // wall-clock time allowed for one run
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.limits.set_time_limit(limit);
+    }
+
+    // setting it, e.g. from another thread, stops the running script at its next statement;
+    // it stays set, and keeps stopping runs, until the host clears it
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.limits.interrupt_handle()
+    }
+
+    // Rc frees everything but cycles, e.g. a function stored in the scope it closes over;
+    // those are collected once 'threshold' scopes or upvalues were allocated since the last run
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.heap.borrow_mut().set_threshold(threshold);
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.borrow().stats()
+    }
+
+    // returns how many objects were freed from unreachable cycles
+    pub fn collect_garbage(&mut self) -> usize {
+        self.heap.borrow_mut().collect()
+    }
+
+    // called where scopes and closures are allocated, with no borrows held
+    fn maybe_collect_garbage(&mut self) {
+        if self.heap.borrow().should_collect() {
+            self.collect_garbage();
+        }
     }
 
     pub fn interpret(&mut self, statements: &[Stmt]) {
+        self.with_stack_guard(|interpreter| interpreter.interpret_here(statements))
+    }
+
+    fn interpret_here(&mut self, statements: &[Stmt]) {
+        self.limits.start();
+        let result = match self.backend {
+            Backend::TreeWalk => statements
+                .iter()
+                .try_for_each(|stmt| self.evaluate_statement(stmt)),
+            Backend::Bytecode => self
+                .run_compiled(statements, ScriptMode::Run, self.globals.clone())
+                .map(drop),
+        };
+
+        if let Err(e) = result {
+            self.report(e);
+        }
+    }
+
+    // runs without reporting; the value is that of a trailing expression statement, else nil
+    pub fn run(&mut self, statements: &[Stmt]) -> Result<Value, LoxError> {
+        self.with_stack_guard(|interpreter| interpreter.run_here(statements))
+    }
+
+    fn run_here(&mut self, statements: &[Stmt]) -> Result<Value, LoxError> {
+        self.limits.start();
+        if self.backend == Backend::Bytecode {
+            return self
+                .run_compiled(statements, ScriptMode::Value, self.globals.clone())
+                .map_err(RuntimeSignal::into_error);
+        }
+
+        let mut last = Value::Nil;
         for stmt in statements {
-            if let Err(e) = self.evaluate_statement(stmt) {
-                match e {
-                    RuntimeSignal::Error(_) => eprintln!("{e}"),
-                    RuntimeSignal::Return(_) => {
-                        eprintln!("Should not be returning from top level")
-                    }
+            let result = match stmt {
+                Stmt::Expression(expr) => self
+                    .limits
+                    .check()
+                    .and_then(|()| self.evaluate_expression(expr)),
+                stmt => self.evaluate_statement(stmt).map(|()| Value::Nil),
+            };
+            last = result.map_err(RuntimeSignal::into_error)?;
+        }
+        Ok(last)
+    }
+
+    // same as interpret, but prints the value of bare expression statements (REPL)
+    pub fn interpret_and_echo(&mut self, statements: &[Stmt]) {
+        self.with_stack_guard(|interpreter| interpreter.interpret_and_echo_here(statements))
+    }
+
+    fn interpret_and_echo_here(&mut self, statements: &[Stmt]) {
+        self.limits.start();
+        let result = match self.backend {
+            Backend::TreeWalk => statements.iter().try_for_each(|stmt| match stmt {
+                Stmt::Expression(expr) => {
+                    self.limits.check()?;
+                    let value = self.evaluate_expression(expr)?;
+                    self.write_output(&value.to_string())
                 }
-                return;
-            }
+                stmt => self.evaluate_statement(stmt),
+            }),
+            Backend::Bytecode => self
+                .run_compiled(statements, ScriptMode::Echo, self.globals.clone())
+                .map(drop),
+        };
+
+        if let Err(e) = result {
+            self.report(e);
         }
     }
 
+    fn report(&mut self, signal: RuntimeSignal) {
+        let error = signal.into_error();
+        let message = match &self.source {
+            Some(source) => source.render(&error),
+            None => error.to_string(),
+        };
+        // nowhere left to report a failing diagnostics sink
+        let _ = writeln!(self.stderr, "{message}");
+    }
+
+    fn write_output(&mut self, text: &str) -> Result<(), RuntimeSignal> {
+        writeln!(self.stdout, "{text}").map_err(|err| {
+            RuntimeSignal::native_error(format!("Failed to write output: {err}"))
+        })
+    }
+
     fn evaluate_statement(&mut self, stmt: &Stmt) -> Result<(), RuntimeSignal> {
+        self.limits.check()?;
+        if self.debugger.is_some() {
+            self.debug_statement(stmt)?;
+        }
         match stmt {
             Stmt::Expression(expr) => {
                 self.evaluate_expression(expr)?;
                 Ok(())
             }
-            Stmt::Print(expr) => {
+            Stmt::Print(_, expr) => {
                 let value = self.evaluate_expression(expr)?;
-                println!("{}", value.as_string());
+                self.write_output(&value.as_string())
+            }
+            Stmt::Var(token, initializer) => {
+                let value = match initializer {
+                    Some(expr) => Some(self.evaluate_expression(expr)?),
+                    None => None,
+                };
+                self.define_variable(token, value);
                 Ok(())
             }
-            Stmt::Var(token, initializer) => match initializer {
-                Some(expr) => {
-                    let value = self.evaluate_expression(expr)?;
-                    self.environment
-                        .borrow_mut()
-                        .define(token.lexeme.clone(), value);
-                    Ok(())
-                }
-                None => {
-                    self.environment
-                        .borrow_mut()
-                        .define(token.lexeme.clone(), Value::Nil);
-                    Ok(())
-                }
-            },
             Stmt::Block(statements) => self.execute_block(statements, self.environment.clone()),
             Stmt::If(conditions) => {
                 if self.evaluate_expression(&conditions.condition)?.is_truthy() {
@@ -113,19 +350,57 @@ impl Interpreter {
                 Ok(())
             }
             Stmt::While(conditions) => {
+                let label = conditions.label.as_ref().map(|token| token.lexeme.as_str());
+                let targets_this_loop =
+                    |target: &Option<String>| target.is_none() || target.as_deref() == label;
+
                 while self.evaluate_expression(&conditions.condition)?.is_truthy() {
-                    self.evaluate_statement(&conditions.stmt_body)?;
+                    match self.evaluate_statement(&conditions.stmt_body) {
+                        Ok(()) => {}
+                        Err(RuntimeSignal::Break(target)) if targets_this_loop(&target) => break,
+                        Err(RuntimeSignal::Continue(target)) if targets_this_loop(&target) => {}
+                        Err(e) => return Err(e),
+                    }
+
+                    if let Some(increment) = &conditions.increment {
+                        self.evaluate_expression(increment)?;
+                    }
                 }
                 Ok(())
             }
+            Stmt::Break(_, label) => Err(RuntimeSignal::Break(
+                label.as_ref().map(|token| token.lexeme.clone()),
+            )),
+            Stmt::Continue(_, label) => Err(RuntimeSignal::Continue(
+                label.as_ref().map(|token| token.lexeme.clone()),
+            )),
             Stmt::Function(fun_def) => {
                 let function = Value::Callable(Rc::new(LoxCallable::lox_function(
                     fun_def.clone(),
                     self.environment.clone(),
                 )));
-                self.environment
-                    .borrow_mut()
-                    .define(fun_def.name.lexeme.clone(), function);
+                self.define_variable(&fun_def.name, Some(function));
+                Ok(())
+            }
+            Stmt::Class(class_def) => {
+                let methods = class_def
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        let name = method.name.lexeme.clone();
+                        let function = LoxCallable::LoxFunction {
+                            closure: self.environment.clone(),
+                            fun_def: method.clone(),
+                            is_initializer: name == "init",
+                        };
+                        (name, function)
+                    })
+                    .collect();
+                let class = Value::Class(Rc::new(LoxClass::new(
+                    class_def.name.lexeme.clone(),
+                    methods,
+                )));
+                self.define_variable(&class_def.name, Some(class));
                 Ok(())
             }
             Stmt::Return(_, expr) => {
@@ -136,14 +411,48 @@ impl Interpreter {
                 };
                 Err(RuntimeSignal::Return(value))
             }
+            Stmt::Throw(keyword, expr) => {
+                let value = self.evaluate_expression(expr)?;
+                Err(throw_value(keyword, value))
+            }
+            Stmt::Try(try_stmt) => self.execute_try(try_stmt),
+            Stmt::Export(declaration) => {
+                self.evaluate_statement(declaration)?;
+                if let Some(name) = declaration.declared_name() {
+                    self.modules.export(name.lexeme.clone());
+                }
+                Ok(())
+            }
+            Stmt::Import(import) => self.execute_import(import),
         }
     }
 
+    fn execute_try(&mut self, try_stmt: &TryStatement) -> Result<(), RuntimeSignal> {
+        let mut result = self.execute_block(&try_stmt.body, self.environment.clone());
+
+        if let Some(catch) = &try_stmt.catch
+            && let Err(RuntimeSignal::Error(err)) = &result
+            && err.kind() == ErrorKind::Runtime
+        {
+            let caught = caught_value(err);
+            let env = Environment::new_env_ref(self.environment.clone());
+            env.borrow_mut().push(&catch.name.lexeme, Some(caught));
+            result = self.execute_block(&catch.body, env);
+        }
+
+        // an error or jump out of 'finally' replaces whatever the try/catch produced
+        if let Some(finally) = &try_stmt.finally {
+            self.execute_block(finally, self.environment.clone())?;
+        }
+        result
+    }
+
     pub fn execute_block(
         &mut self,
         statements: &[Stmt],
         enclosing: EnvRef,
     ) -> Result<(), RuntimeSignal> {
+        self.maybe_collect_garbage();
         let previous = self.environment.clone();
         self.environment = Environment::new_env_ref(enclosing.clone());
 
@@ -163,7 +472,19 @@ impl Interpreter {
         match expr {
             Expr::Assignment { name, value, .. } => {
                 let right_value = self.evaluate_expression(value)?;
-                self.environment.borrow_mut().assign(name, &right_value)?;
+                match self.locals.get(&expr.id()) {
+                    Some(&(distance, slot)) => Environment::assign_at(
+                        &self.environment,
+                        distance,
+                        slot,
+                        name,
+                        &right_value,
+                    )?,
+                    None => self
+                        .current_globals()
+                        .borrow_mut()
+                        .assign(name, &right_value)?,
+                }
                 Ok(right_value)
             }
             Expr::Logical {
@@ -177,17 +498,107 @@ impl Interpreter {
                 left_expr,
                 operator,
                 right_expr,
-            } => self.evaluate_binary(left_expr, operator, right_expr),
-            Expr::Unary { token, expression, .. } => self.evaluate_unary(token, expression),
+            } => {
+                let left_val = self.evaluate_expression(left_expr)?;
+                let right_val = self.evaluate_expression(right_expr)?;
+                binary_op(left_val, operator, right_val)
+            }
+            Expr::Unary { token, expression, .. } => {
+                let right_val = self.evaluate_expression(expression)?;
+                unary_op(token, right_val)
+            }
             Expr::Call {
                 id: _,
                 callee,
                 paren,
                 arguments,
             } => self.evaluate_call(callee, paren, arguments),
+            Expr::List { elements, .. } => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate_expression(element)?);
+                }
+                Ok(Value::list(values))
+            }
+            Expr::Lambda { fun_def, .. } => Ok(Value::Callable(Rc::new(
+                LoxCallable::lox_function(fun_def.clone(), self.environment.clone()),
+            ))),
+            Expr::Map { brace, entries, .. } => {
+                let mut map = LoxMap::default();
+                for (key, value) in entries {
+                    let key = self.evaluate_expression(key)?;
+                    let key = map_key(&key, brace)?;
+                    map.insert(key, self.evaluate_expression(value)?);
+                }
+                Ok(Value::map(map))
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => {
+                let object = self.evaluate_expression(object)?;
+                let index = self.evaluate_expression(index)?;
+                index_get(object, index, bracket)
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+                ..
+            } => {
+                let object = self.evaluate_expression(object)?;
+                let index = self.evaluate_expression(index)?;
+                let value = self.evaluate_expression(value)?;
+                index_set(object, index, value, bracket)
+            }
+            Expr::Get { object, name, .. } => {
+                let object = self.evaluate_expression(object)?;
+                get_property(object, name)
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => {
+                let instance = expect_instance(self.evaluate_expression(object)?, name)?;
+                let value = self.evaluate_expression(value)?;
+                instance.borrow_mut().set(name, value.clone());
+                Ok(value)
+            }
+            Expr::This { keyword, .. } => self.look_up_variable(keyword, expr),
             Expr::Grouping { expression, .. } => self.evaluate_expression(expression),
-            Expr::Literal { value, .. } => Ok(self.literal_to_value(value)),
-            Expr::Variable { token, .. } => self.environment.borrow().get(token),
+            Expr::Literal { value, .. } => Ok(literal_value(value)),
+            Expr::Variable { token, .. } => self.look_up_variable(token, expr),
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token, expr: &Expr) -> Result<Value, RuntimeSignal> {
+        match self.locals.get(&expr.id()) {
+            Some(&(distance, slot)) => Environment::get_at(&self.environment, distance, slot, name),
+            None => self.current_globals().borrow().get(name),
+        }
+    }
+
+    // each module has its own globals, which every environment of the module points to
+    fn current_globals(&self) -> GlobalsRef {
+        self.environment.borrow().globals().clone()
+    }
+
+    // top level declarations are globals; anything else takes the scope's next slot
+    fn define_variable(&mut self, name: &Token, value: Option<Value>) {
+        let mut env = self.environment.borrow_mut();
+        if env.is_top_level() {
+            let mut globals = env.globals().borrow_mut();
+            match value {
+                Some(value) => globals.define(name.lexeme.clone(), value),
+                None => globals.declare(name.lexeme.clone()),
+            }
+        } else {
+            env.push(&name.lexeme, value);
         }
     }
 
@@ -205,24 +616,39 @@ impl Interpreter {
             argument_values.push(self.evaluate_expression(expr_arguments)?);
         }
 
-        if let Value::Callable(lox_callable) = callee_value {
-            if argument_values.len() != lox_callable.arity() {
-                return Err(RuntimeSignal::runtime_error(
-                    paren.clone(),
-                    format!(
-                        "Expected {} arguments, but got {}",
-                        lox_callable.arity(),
-                        argument_values.len()
-                    ),
-                ));
-            }
-            lox_callable.call(self, argument_values)
-        } else {
-            Err(RuntimeSignal::runtime_error(
+        let function = check_call(&callee_value, argument_values.len(), paren, callee)?;
+        self.check_call_depth(paren)?;
+        self.call_stack.push(CallFrame {
+            function,
+            line: paren.line,
+        });
+
+        let mut result = match callee_value {
+            Value::Class(class) => LoxClass::call(&class, self, argument_values),
+            Value::Callable(lox_callable) => lox_callable.call(self, argument_values),
+            _ => unreachable!("non callable values are rejected above"),
+        };
+
+        // the deepest frame sees the error first, so it records the full stack
+        if let Err(RuntimeSignal::Error(err)) = &mut result
+            && err.trace().is_empty()
+        {
+            err.locate_at(paren);
+            err.set_trace(self.call_stack.clone());
+        }
+
+        self.call_stack.pop();
+        result
+    }
+
+    fn check_call_depth(&self, paren: &Token) -> Result<(), RuntimeSignal> {
+        if self.call_stack.len() >= self.max_call_depth || self.stack_exhausted() {
+            return Err(RuntimeSignal::runtime_error(
                 paren.clone(),
-                format!("Expr not callable: {callee}"),
-            ))
+                "stack overflow".to_string(),
+            ));
         }
+        Ok(())
     }
 
     fn evaluate_logical(
@@ -243,143 +669,277 @@ impl Interpreter {
 
         self.evaluate_expression(right_expr)
     }
+}
 
-    fn literal_to_value(&self, literal: &LiteralValue) -> Value {
-        match literal {
-            LiteralValue::Nil => Value::Nil,
-            LiteralValue::Number(n) => Value::Number(*n),
-            LiteralValue::Boolean(b) => Value::Boolean(*b),
-            LiteralValue::String(s) => Value::String(Rc::new(s.clone())),
+// the value of the 'object[index]' expression
+pub(crate) fn index_get(object: Value, index: Value, bracket: &Token) -> Result<Value, RuntimeSignal> {
+    match object {
+        Value::List(list) => {
+            let list = list.borrow();
+            let i = list_index(&index, list.len(), bracket)?;
+            Ok(list[i].clone())
         }
+        Value::Map(map) => {
+            let key = map_key(&index, bracket)?;
+            map.borrow().get(&key).cloned().ok_or_else(|| {
+                RuntimeSignal::runtime_error(bracket.clone(), format!("Key {index} not found in map"))
+            })
+        }
+        _ => Err(RuntimeSignal::runtime_error(
+            bracket.clone(),
+            format!("Can only index into lists and maps, not {object}"),
+        )),
     }
+}
 
-    fn evaluate_unary(
-        &mut self,
-        operator: &Token,
-        expression: &Expr,
-    ) -> Result<Value, RuntimeSignal> {
-        let right_val = self.evaluate_expression(expression)?;
+pub(crate) fn index_set(
+    object: Value,
+    index: Value,
+    value: Value,
+    bracket: &Token,
+) -> Result<Value, RuntimeSignal> {
+    match object {
+        Value::List(list) => {
+            let mut list = list.borrow_mut();
+            let i = list_index(&index, list.len(), bracket)?;
+            list[i] = value.clone();
+            Ok(value)
+        }
+        Value::Map(map) => {
+            let key = map_key(&index, bracket)?;
+            map.borrow_mut().insert(key, value.clone());
+            Ok(value)
+        }
+        _ => Err(RuntimeSignal::runtime_error(
+            bracket.clone(),
+            format!("Can only assign into lists and maps, not {object}"),
+        )),
+    }
+}
 
-        match operator.token_type {
-            TokenType::Minus => {
-                if right_val.is_numeric() {
-                    Ok(Value::Number(-right_val.as_number()))
-                } else {
-                    Err(RuntimeSignal::runtime_error(
-                        operator.clone(),
-                        "{-} operation attempted on non numeric type".to_string(),
-                    ))
-                }
-            }
-            TokenType::Bang => Ok(Value::Boolean(!right_val.is_truthy())),
-            _ => panic!(
-                "evalute unary called when operator is neither Minus or Bang, 
-                \nOperator: {operator}",
-            ),
+pub(crate) fn get_property(object: Value, name: &Token) -> Result<Value, RuntimeSignal> {
+    match object {
+        Value::Instance(instance) => LoxInstance::get(&instance, name),
+        Value::Error(err) => error_property(&err, name),
+        _ => Err(RuntimeSignal::runtime_error(
+            name.clone(),
+            "Only instances have properties".into(),
+        )),
+    }
+}
+
+// the target of a field assignment, checked before the assigned value is evaluated
+pub(crate) fn expect_instance(
+    object: Value,
+    name: &Token,
+) -> Result<Rc<RefCell<LoxInstance>>, RuntimeSignal> {
+    match object {
+        Value::Instance(instance) => Ok(instance),
+        _ => Err(RuntimeSignal::runtime_error(
+            name.clone(),
+            "Only instances have fields".into(),
+        )),
+    }
+}
+
+// checks a call before it is made, returning the name its call frame is listed under
+pub(crate) fn check_call(
+    callee_value: &Value,
+    argument_count: usize,
+    paren: &Token,
+    callee: &dyn fmt::Display,
+) -> Result<String, RuntimeSignal> {
+    let (arity, function) = match callee_value {
+        Value::Callable(lox_callable) => (lox_callable.arity(), lox_callable.name()),
+        Value::Class(class) => (class.arity(), class.name.clone()),
+        _ => {
+            return Err(RuntimeSignal::runtime_error(
+                paren.clone(),
+                format!("Expr not callable: {callee}"),
+            ))
         }
+    };
+
+    if !arity.accepts(argument_count) {
+        return Err(RuntimeSignal::runtime_error(
+            paren.clone(),
+            format!("Expected {arity} arguments, but got {argument_count}"),
+        ));
     }
+    Ok(function)
+}
 
-    fn evaluate_binary(
-        &mut self,
-        left_expr: &Expr,
-        operator: &Token,
-        right_expr: &Expr,
-    ) -> Result<Value, RuntimeSignal> {
-        let left_val = self.evaluate_expression(left_expr)?;
-        let right_val = self.evaluate_expression(right_expr)?;
-
-        match operator.token_type {
-            // ============ numeric comparison =============
-            TokenType::Greater => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Boolean(left_val.as_number() > right_val.as_number()))
-            }
-            TokenType::GreaterEqual => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Boolean(
-                    left_val.as_number() >= right_val.as_number(),
-                ))
-            }
-            TokenType::Less => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Boolean(left_val.as_number() < right_val.as_number()))
-            }
-            TokenType::LessEqual => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Boolean(
-                    left_val.as_number() <= right_val.as_number(),
-                ))
-            }
+// what 'throw' raises; rethrowing a caught error keeps its original message and location
+pub(crate) fn throw_value(keyword: &Token, value: Value) -> RuntimeSignal {
+    match value {
+        Value::Error(err) => RuntimeSignal::Error((*err).clone()),
+        value => RuntimeSignal::thrown(keyword, value),
+    }
+}
 
-            // ============ equality ================
-            TokenType::EqualEqual => Ok(Value::Boolean(left_val == right_val)),
-            TokenType::BangEqual => Ok(Value::Boolean(left_val != right_val)),
+// what a catch clause binds: the thrown value, or the error itself for interpreter errors
+pub(crate) fn caught_value(err: &LoxError) -> Value {
+    match err.thrown() {
+        Some(value) => value.clone(),
+        None => Value::Error(Rc::new(err.clone())),
+    }
+}
 
-            // ============ arithmetic ============
-            TokenType::Minus => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Number(left_val.as_number() - right_val.as_number()))
-            }
-            TokenType::Slash => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Number(left_val.as_number() / right_val.as_number()))
-            }
-            TokenType::Star => {
-                self.both_are_numeric(&left_val, operator, &right_val)?;
-                Ok(Value::Number(left_val.as_number() * right_val.as_number()))
-            }
+pub(crate) fn literal_value(literal: &LiteralValue) -> Value {
+    match literal {
+        LiteralValue::Nil => Value::Nil,
+        LiteralValue::Number(n) => Value::Number(*n),
+        LiteralValue::Boolean(b) => Value::Boolean(*b),
+        LiteralValue::String(s) => Value::String(Rc::new(s.clone())),
+    }
+}
 
-            // =========== arithmeitc and string concact ============
-            TokenType::Plus => {
-                if let Ok(true) = self.both_are_numeric(&left_val, operator, &right_val) {
-                    Ok(Value::Number(left_val.as_number() + right_val.as_number()))
-                } else {
-                    let s = self.concatenate_strings(left_val, operator, right_val)?;
-                    Ok(Value::String(Rc::new(s)))
-                }
+pub(crate) fn unary_op(operator: &Token, right_val: Value) -> Result<Value, RuntimeSignal> {
+    match operator.token_type {
+        TokenType::Minus => {
+            if right_val.is_numeric() {
+                Ok(Value::Number(-right_val.as_number()))
+            } else {
+                Err(RuntimeSignal::runtime_error(
+                    operator.clone(),
+                    "{-} operation attempted on non numeric type".to_string(),
+                ))
             }
-            _ => panic!("Evaluate Unary called on invalid operator: {operator}"),
         }
+        TokenType::Bang => Ok(Value::Boolean(!right_val.is_truthy())),
+        _ => panic!(
+            "evalute unary called when operator is neither Minus or Bang, 
+                \nOperator: {operator}",
+        ),
     }
+}
 
-    fn both_are_numeric(
-        &self,
-        left_val: &Value,
-        operator: &Token,
-        right_val: &Value,
-    ) -> Result<bool, RuntimeSignal> {
-        if left_val.is_numeric() && right_val.is_numeric() {
-            Ok(true)
-        } else {
-            let token_type = operator.token_type.clone();
-            Err(RuntimeSignal::runtime_error(
-                operator.clone(),
-                format!("'{}' operation attempted on non numeric types", token_type),
+pub(crate) fn binary_op(
+    left_val: Value,
+    operator: &Token,
+    right_val: Value,
+) -> Result<Value, RuntimeSignal> {
+    match operator.token_type {
+        // ============ numeric comparison =============
+        TokenType::Greater => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Boolean(left_val.as_number() > right_val.as_number()))
+        }
+        TokenType::GreaterEqual => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Boolean(
+                left_val.as_number() >= right_val.as_number(),
+            ))
+        }
+        TokenType::Less => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Boolean(left_val.as_number() < right_val.as_number()))
+        }
+        TokenType::LessEqual => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Boolean(
+                left_val.as_number() <= right_val.as_number(),
             ))
         }
+
+        // ============ equality ================
+        TokenType::EqualEqual => Ok(Value::Boolean(left_val == right_val)),
+        TokenType::BangEqual => Ok(Value::Boolean(left_val != right_val)),
+
+        // ============ arithmetic ============
+        TokenType::Minus => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Number(left_val.as_number() - right_val.as_number()))
+        }
+        TokenType::Slash => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Number(left_val.as_number() / right_val.as_number()))
+        }
+        TokenType::Star => {
+            both_are_numeric(&left_val, operator, &right_val)?;
+            Ok(Value::Number(left_val.as_number() * right_val.as_number()))
+        }
+
+        // =========== arithmeitc and string concact ============
+        TokenType::Plus => {
+            if let Ok(true) = both_are_numeric(&left_val, operator, &right_val) {
+                Ok(Value::Number(left_val.as_number() + right_val.as_number()))
+            } else {
+                let s = concatenate_strings(left_val, operator, right_val)?;
+                Ok(Value::String(Rc::new(s)))
+            }
+        }
+        _ => panic!("Evaluate Unary called on invalid operator: {operator}"),
     }
+}
 
-    fn concatenate_strings(
-        &self,
-        left_val: Value,
-        operator: &Token,
-        right_val: Value,
-    ) -> Result<String, RuntimeSignal> {
-        // if either is a string, concat
-        if left_val.is_stringy() || right_val.is_stringy() {
-            Ok(left_val.as_string() + &right_val.as_string())
-        } else {
-            Err(RuntimeSignal::runtime_error(
-                operator.clone(),
-                format!(
-                    "{} operation attempted on binary in which neither are of type String or Number",
-                    operator.token_type.clone())
-                )
+fn both_are_numeric(
+    left_val: &Value,
+    operator: &Token,
+    right_val: &Value,
+) -> Result<bool, RuntimeSignal> {
+    if left_val.is_numeric() && right_val.is_numeric() {
+        Ok(true)
+    } else {
+        let token_type = operator.token_type.clone();
+        Err(RuntimeSignal::runtime_error(
+            operator.clone(),
+            format!("'{}' operation attempted on non numeric types", token_type),
+        ))
+    }
+}
+
+fn concatenate_strings(
+    left_val: Value,
+    operator: &Token,
+    right_val: Value,
+) -> Result<String, RuntimeSignal> {
+    // if either is a string, concat
+    if left_val.is_stringy() || right_val.is_stringy() {
+        Ok(left_val.as_string() + &right_val.as_string())
+    } else {
+        Err(RuntimeSignal::runtime_error(
+            operator.clone(),
+            format!(
+                "{} operation attempted on binary in which neither are of type String or Number",
+                operator.token_type.clone())
             )
-        }
+        )
+    }
+}
+
+// checks that 'index' is a whole number addressing an element of a list of length 'len'
+fn list_index(index: &Value, len: usize, bracket: &Token) -> Result<usize, RuntimeSignal> {
+    match index {
+        Value::Number(n) if n.fract() == 0.0 && *n >= 0.0 && (*n as usize) < len => Ok(*n as usize),
+        Value::Number(n) => Err(RuntimeSignal::runtime_error(
+            bracket.clone(),
+            format!("List index {n} out of range for length {len}"),
+        )),
+        _ => Err(RuntimeSignal::runtime_error(
+            bracket.clone(),
+            format!("List index must be a number, got {index}"),
+        )),
+    }
+}
+
+// the fields a caught error exposes to scripts
+fn error_property(err: &LoxError, name: &Token) -> Result<Value, RuntimeSignal> {
+    match name.lexeme.as_str() {
+        // lox string values carry their quotes
+        "message" => Ok(Value::String(Rc::new(format!("\"{}\"", err.message())))),
+        "line" => Ok(Value::Number(err.line() as f64)),
+        _ => Err(RuntimeSignal::runtime_error(
+            name.clone(),
+            format!("Undefined property '{}' on error", name.lexeme),
+        )),
     }
 }
 
+fn map_key(key: &Value, token: &Token) -> Result<HashKey, RuntimeSignal> {
+    HashKey::from_value(key).map_err(|message| RuntimeSignal::runtime_error(token.clone(), message))
+}
+
 impl Default for Interpreter {
     fn default() -> Self {
         Self::new()
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{fmt, rc::Rc};
 
 use crate::{
     error::RuntimeSignal,
@@ -6,25 +6,46 @@ use crate::{
         environment::{EnvRef, Environment},
         stmt::FunctionDefinition,
         values::Value,
+        vm::Closure,
         Interpreter,
     },
 };
 
-#[derive(Debug)]
+// host functions get the interpreter so they can reach globals, output, or call back into lox
+pub type NativeFn = Rc<dyn Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeSignal>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+#[derive(Clone)]
 pub enum LoxCallable {
     Native {
-        arity: usize,
-        function: fn(Vec<Value>) -> Result<Value, RuntimeSignal>,
+        name: String,
+        arity: Arity,
+        function: NativeFn,
     },
     LoxFunction {
         closure: EnvRef,
         fun_def: Rc<FunctionDefinition>,
+        is_initializer: bool,
+    },
+    // a function compiled for the VM; methods carry the instance they were bound to
+    Compiled {
+        closure: Rc<Closure>,
+        receiver: Option<Value>,
     },
 }
 
 impl LoxCallable {
     pub fn lox_function(fun_def: Rc<FunctionDefinition>, closure: EnvRef) -> Self {
-        LoxCallable::LoxFunction { fun_def, closure }
+        LoxCallable::LoxFunction {
+            fun_def,
+            closure,
+            is_initializer: false,
+        }
     }
 
     pub fn call(
@@ -33,27 +54,126 @@ impl LoxCallable {
         args: Vec<Value>,
     ) -> Result<Value, RuntimeSignal> {
         match self {
-            LoxCallable::Native { arity: _, function } => function(args),
-            LoxCallable::LoxFunction { fun_def, closure } => {
+            LoxCallable::Native { function, .. } => function(interpreter, &args),
+            LoxCallable::LoxFunction {
+                fun_def,
+                closure,
+                is_initializer,
+            } => {
+                // parameters take the first slots of the call's environment
                 let env = Environment::new_env_ref(closure.clone());
-                for (i, param) in fun_def.params.iter().enumerate() {
-                    env.borrow_mut()
-                        .define(param.lexeme.to_string(), args[i].clone());
+                for (param, arg) in fun_def.params.iter().zip(args) {
+                    env.borrow_mut().push(&param.lexeme, Some(arg));
                 }
 
-                match interpreter.execute_block(&fun_def.body, env) {
-                    Ok(()) => Ok(Value::Nil),
-                    Err(RuntimeSignal::Return(value)) => Ok(value.unwrap_or(Value::Nil)),
-                    Err(err) => Err(err),
+                let result = match interpreter.execute_block(&fun_def.body, env) {
+                    Ok(()) => Value::Nil,
+                    Err(RuntimeSignal::Return(value)) => value.unwrap_or(Value::Nil),
+                    Err(err) => return Err(err),
+                };
+
+                // initializers always hand back the instance they were bound to
+                if *is_initializer {
+                    Ok(closure.borrow().get_slot(0).unwrap_or(Value::Nil))
+                } else {
+                    Ok(result)
+                }
+            }
+            LoxCallable::Compiled { closure, receiver } => {
+                interpreter.call_closure(closure, receiver.clone(), args)
+            }
+        }
+    }
+
+    // a method bound to an instance; tree-walked methods see 'this' through an extra environment,
+    // compiled ones in slot 0 of their frame
+    pub fn bind(&self, instance: Value) -> LoxCallable {
+        match self {
+            LoxCallable::Native { .. } => self.clone(),
+            LoxCallable::LoxFunction {
+                closure,
+                fun_def,
+                is_initializer,
+            } => {
+                let env = Environment::new_env_ref(closure.clone());
+                env.borrow_mut().push("this", Some(instance));
+                LoxCallable::LoxFunction {
+                    closure: env,
+                    fun_def: fun_def.clone(),
+                    is_initializer: *is_initializer,
                 }
             }
+            LoxCallable::Compiled { closure, .. } => LoxCallable::Compiled {
+                closure: closure.clone(),
+                receiver: Some(instance),
+            },
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            LoxCallable::Native { name, .. } => name.clone(),
+            LoxCallable::LoxFunction { fun_def, .. } => fun_def.name.lexeme.clone(),
+            LoxCallable::Compiled { closure, .. } => closure.function().name.clone(),
         }
     }
 
-    pub fn arity(&self) -> usize {
+    pub fn arity(&self) -> Arity {
+        match self {
+            LoxCallable::Native { arity, .. } => *arity,
+            LoxCallable::LoxFunction { fun_def, .. } => Arity::Exact(fun_def.params.len()),
+            LoxCallable::Compiled { closure, .. } => Arity::Exact(closure.function().arity),
+        }
+    }
+}
+
+impl fmt::Debug for LoxCallable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxCallable::Native { name, arity, .. } => f
+                .debug_struct("Native")
+                .field("name", name)
+                .field("arity", arity)
+                .finish_non_exhaustive(),
+            LoxCallable::LoxFunction {
+                fun_def,
+                is_initializer,
+                ..
+            } => f
+                .debug_struct("LoxFunction")
+                .field("name", &fun_def.name.lexeme)
+                .field("is_initializer", is_initializer)
+                .finish_non_exhaustive(),
+            LoxCallable::Compiled { closure, receiver } => f
+                .debug_struct("Compiled")
+                .field("name", &closure.function().name)
+                .field("is_initializer", &closure.function().is_initializer)
+                .field("bound", &receiver.is_some())
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Self {
+        Arity::Exact(n)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LoxCallable::Native { arity, function: _ } => *arity,
-            LoxCallable::LoxFunction { fun_def, .. } => fun_def.params.len(),
+            Arity::Exact(n) => write!(f, "{n}"),
+            Arity::AtLeast(n) => write!(f, "at least {n}"),
         }
     }
 }
@@ -0,0 +1,100 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    error::RuntimeSignal,
+    interpreter::{
+        callable::{Arity, LoxCallable},
+        heap::Tracer,
+        values::Value,
+        Interpreter,
+    },
+    scanner::token::Token,
+};
+
+// methods are unbound callables from either backend; 'bind' supplies 'this'
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    methods: HashMap<String, LoxCallable>,
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxClass {
+    pub fn new(name: String, methods: HashMap<String, LoxCallable>) -> Self {
+        LoxClass { name, methods }
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.methods
+            .get("init")
+            .map_or(Arity::Exact(0), |initializer| initializer.arity())
+    }
+
+    pub fn bind(&self, name: &str, instance: Value) -> Option<LoxCallable> {
+        Some(self.methods.get(name)?.bind(instance))
+    }
+
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        self.methods.values().for_each(|method| tracer.callable(method));
+    }
+
+    pub fn call(
+        class: &Rc<LoxClass>,
+        interpreter: &mut Interpreter,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeSignal> {
+        let instance = LoxInstance::instantiate(class.clone());
+
+        if let Some(initializer) = class.bind("init", instance.clone()) {
+            initializer.call(interpreter, args)?;
+        }
+
+        Ok(instance)
+    }
+}
+
+impl LoxInstance {
+    pub fn instantiate(class: Rc<LoxClass>) -> Value {
+        Value::Instance(Rc::new(RefCell::new(LoxInstance {
+            class,
+            fields: HashMap::new(),
+        })))
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Value, RuntimeSignal> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let class = instance.borrow().class.clone();
+        match class.bind(&name.lexeme, Value::Instance(instance.clone())) {
+            Some(method) => Ok(Value::Callable(Rc::new(method))),
+            None => Err(RuntimeSignal::runtime_error(
+                name.clone(),
+                format!("Undefined property '{}'", name.lexeme),
+            )),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.lexeme.clone(), value);
+    }
+
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        tracer.class(&self.class);
+        self.fields.values().for_each(|value| tracer.value(value));
+    }
+
+    pub(super) fn take_fields(&mut self) -> HashMap<String, Value> {
+        std::mem::take(&mut self.fields)
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    error::{ErrorKind, RuntimeSignal},
+    interpreter::{
+        callable::LoxCallable, environment::EnvRef, stmt::Stmt, values::Value, Interpreter,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseReason {
+    Entry, // the first statement of the script
+    Breakpoint,
+    Step,
+}
+
+// how execution carries on after a pause
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,   // to the next statement, wherever it is
+    StepOver, // to the next statement not inside a call made from here
+    StepOut,  // to the next statement after the current function returns
+    Stop,     // abandon the script
+}
+
+// a function in the middle of running, at the line it is on
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    pub line: usize,
+}
+
+// the variables of one environment; None is a variable declared without a value
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub name: String,
+    pub variables: Vec<(String, Option<Value>)>,
+}
+
+// what a front end can look at and change while execution is paused
+pub struct Pause<'a> {
+    pub reason: PauseReason,
+    pub line: usize,
+    pub frames: Vec<StackFrame>, // innermost first, ending with the script itself
+    pub scopes: Vec<Scope>,      // the environment chain, innermost first, then the globals
+    pub breakpoints: &'a mut BTreeSet<usize>,
+}
+
+pub trait DebugFrontend {
+    // blocks until the user decides how to carry on
+    fn paused(&mut self, pause: Pause) -> Resume;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Continue,
+    StepIn,
+    StepOver(usize), // call depth stepping started at
+    StepOut(usize),
+    Stopped,
+}
+
+// decides where a running script pauses; it starts out paused on the first statement
+pub struct Debugger {
+    frontend: Box<dyn DebugFrontend>,
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    // line and call depth of the last statement seen; a line with several statements on it,
+    // such as a one-line loop, only pauses as it is entered
+    position: Option<(usize, usize)>,
+}
+
+impl Debugger {
+    pub fn new(frontend: impl DebugFrontend + 'static) -> Self {
+        Debugger {
+            frontend: Box::new(frontend),
+            breakpoints: BTreeSet::new(),
+            mode: Mode::StepIn,
+            position: None,
+        }
+    }
+
+    pub fn set_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    // run to the first breakpoint instead of pausing on the first statement
+    pub fn skip_entry(&mut self) {
+        self.mode = Mode::Continue;
+    }
+
+    fn should_pause(&mut self, line: usize, depth: usize) -> Option<PauseReason> {
+        if self.position == Some((line, depth)) {
+            return None;
+        }
+        let entry = self.position.is_none();
+        self.position = Some((line, depth));
+
+        if self.breakpoints.contains(&line) {
+            return Some(PauseReason::Breakpoint);
+        }
+        match self.mode {
+            Mode::StepIn if entry => Some(PauseReason::Entry),
+            Mode::StepIn => Some(PauseReason::Step),
+            Mode::StepOver(from) if depth <= from => Some(PauseReason::Step),
+            Mode::StepOut(from) if depth < from => Some(PauseReason::Step),
+            _ => None,
+        }
+    }
+
+    fn pause(
+        &mut self,
+        reason: PauseReason,
+        line: usize,
+        depth: usize,
+        frames: Vec<StackFrame>,
+        scopes: Vec<Scope>,
+    ) {
+        let pause = Pause {
+            reason,
+            line,
+            frames,
+            scopes,
+            breakpoints: &mut self.breakpoints,
+        };
+        self.mode = match self.frontend.paused(pause) {
+            Resume::Continue => Mode::Continue,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+            Resume::Stop => Mode::Stopped,
+        };
+    }
+}
+
+impl Interpreter {
+    // the tree-walker pauses wherever the debugger asks; the bytecode backend never does
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.environment.borrow_mut().record_names();
+        self.debugger = Some(debugger);
+    }
+
+    // gives an attached debugger the chance to pause before 'stmt' runs
+    pub(super) fn debug_statement(&mut self, stmt: &Stmt) -> Result<(), RuntimeSignal> {
+        let Some(mut debugger) = self.debugger.take() else {
+            return Ok(());
+        };
+
+        let depth = self.call_stack.len();
+        if let Some(line) = stmt.line()
+            && let Some(reason) = debugger.should_pause(line, depth)
+        {
+            debugger.pause(reason, line, depth, self.stack_frames(line), self.scopes());
+        }
+
+        let stopped = debugger.mode == Mode::Stopped;
+        self.debugger = Some(debugger);
+        if stopped {
+            return Err(RuntimeSignal::limit_error(
+                ErrorKind::Interrupted,
+                "stopped by the debugger".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn stack_frames(&self, line: usize) -> Vec<StackFrame> {
+        // each call frame records the line it was called from, which is where its caller is
+        let mut frames = Vec::new();
+        let mut line = line;
+        for frame in self.call_stack.iter().rev() {
+            frames.push(StackFrame {
+                function: frame.function.clone(),
+                line,
+            });
+            line = frame.line;
+        }
+        frames.push(StackFrame {
+            function: "script".to_string(),
+            line,
+        });
+        frames
+    }
+
+    fn scopes(&self) -> Vec<Scope> {
+        let mut scopes = Vec::new();
+        let mut env: Option<EnvRef> = Some(self.environment.clone());
+        while let Some(current) = env {
+            let current = current.borrow();
+            if !current.is_top_level() {
+                let name = if scopes.is_empty() {
+                    "local".to_string()
+                } else {
+                    format!("enclosing {}", scopes.len())
+                };
+                scopes.push(Scope {
+                    name,
+                    variables: current.variables(),
+                });
+            }
+            env = current.enclosing().cloned();
+        }
+
+        // natives are in every program, so they would only bury the script's own globals
+        let globals = self.current_globals();
+        let mut variables: Vec<(String, Option<Value>)> = globals
+            .borrow()
+            .bindings()
+            .filter(|(_, value)| !is_native(value))
+            .map(|(name, value)| (name.clone(), Some(value.clone())))
+            .collect();
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+        scopes.push(Scope {
+            name: "globals".to_string(),
+            variables,
+        });
+        scopes
+    }
+}
+
+fn is_native(value: &Value) -> bool {
+    matches!(value, Value::Callable(callable) if matches!(**callable, LoxCallable::Native { .. }))
+}
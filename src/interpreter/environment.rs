@@ -1,69 +1,241 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{error::RuntimeSignal, interpreter::values::Value, scanner::token::Token};
+use crate::{
+    error::RuntimeSignal,
+    interpreter::{
+        heap::{HeapRef, Tracer},
+        values::Value,
+    },
+    scanner::token::Token,
+};
 
 pub type EnvRef = Rc<RefCell<Environment>>;
+pub type GlobalsRef = Rc<RefCell<Globals>>;
 
+// the named variables at the top level of a file; everything the resolver didn't find
+// in a local scope is looked up here by name
 #[derive(Debug, Clone, Default)]
+pub struct Globals {
+    // None marks a variable that was declared without an initializer and never assigned
+    values: HashMap<String, Option<Value>>,
+}
+
+// a local scope: variables live in the slots the resolver numbered them with, in
+// declaration order, so they are defined by pushing
 pub struct Environment {
     enclosing: Option<EnvRef>,
-    values: HashMap<String, Value>,
+    values: Vec<Option<Value>>,
+    // the slots' variable names, only kept once a debugger asked for them
+    names: Option<Vec<String>>,
+    globals: GlobalsRef,
+    heap: HeapRef, // every scope is tracked so cycles through it can be collected
+}
+
+impl Globals {
+    pub fn new_ref() -> GlobalsRef {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, Some(value));
+    }
+
+    pub fn declare(&mut self, name: String) {
+        self.values.insert(name, None);
+    }
+
+    // initialized variables, e.g. to copy natives into a module's globals
+    pub fn bindings(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name, value)))
+    }
+
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        self.values.values().flatten().for_each(|value| tracer.value(value));
+    }
+
+    pub(super) fn take_values(&mut self) -> HashMap<String, Option<Value>> {
+        std::mem::take(&mut self.values)
+    }
+
+    pub fn get_own(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned().flatten()
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeSignal> {
+        match self.values.get(&name.lexeme) {
+            Some(Some(value)) => Ok(value.clone()),
+            Some(None) => Err(uninitialized(name)),
+            None => Err(RuntimeSignal::static_error_at(
+                name,
+                format!("Undefined Variable '{}'", name.lexeme),
+            )),
+        }
+    }
+
+    pub fn assign(&mut self, left: &Token, right: &Value) -> Result<(), RuntimeSignal> {
+        match self.values.get_mut(&left.lexeme) {
+            Some(value) => {
+                *value = Some(right.clone());
+                Ok(())
+            }
+            None => {
+                let err_msg = format!("invalid assignment target: {left}");
+                Err(RuntimeSignal::runtime_error(left.clone(), err_msg))
+            }
+        }
+    }
 }
 
 impl Environment {
-    pub fn new(enclosing: EnvRef) -> Self {
-        Environment {
+    // the scope a file's top level runs in; declarations there go to 'globals'
+    pub fn top_level(globals: GlobalsRef, heap: HeapRef) -> EnvRef {
+        Self::tracked(Environment {
+            enclosing: None,
+            values: Vec::new(),
+            names: None,
+            globals,
+            heap,
+        })
+    }
+
+    pub fn new_env_ref(enclosing: EnvRef) -> EnvRef {
+        let (globals, heap, names) = {
+            let enclosing = enclosing.borrow();
+            let names = enclosing.names.as_ref().map(|_| Vec::new());
+            (enclosing.globals.clone(), enclosing.heap.clone(), names)
+        };
+        Self::tracked(Environment {
             enclosing: Some(enclosing),
-            values: HashMap::new(),
+            values: Vec::new(),
+            names,
+            globals,
+            heap,
+        })
+    }
+
+    fn tracked(env: Environment) -> EnvRef {
+        let heap = env.heap.clone();
+        let env = Rc::new(RefCell::new(env));
+        heap.borrow_mut().track_environment(&env);
+        env
+    }
+
+    pub fn is_top_level(&self) -> bool {
+        self.enclosing.is_none()
+    }
+
+    pub fn globals(&self) -> &GlobalsRef {
+        &self.globals
+    }
+
+    pub fn enclosing(&self) -> Option<&EnvRef> {
+        self.enclosing.as_ref()
+    }
+
+    // the next slot; None declares it without an initializer
+    pub fn push(&mut self, name: &str, value: Option<Value>) {
+        self.values.push(value);
+        if let Some(names) = &mut self.names {
+            names.push(name.to_string());
         }
     }
 
-    pub fn new_env_ref(enclosing: impl Into<Option<EnvRef>>) -> EnvRef {
-        if let Some(env_ref) = enclosing.into() {
-            Rc::new(RefCell::new(Self::new(env_ref)))
-        } else {
-            Rc::new(RefCell::new(Self::default()))
+    // scopes created under this one from now on remember their variables' names
+    pub fn record_names(&mut self) {
+        if self.names.is_none() {
+            self.names = Some(vec![String::new(); self.values.len()]);
         }
     }
 
-    pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+    // name and value of each slot, in declaration order; unnamed slots show their number
+    pub fn variables(&self) -> Vec<(String, Option<Value>)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(slot, value)| {
+                let name = self
+                    .names
+                    .as_ref()
+                    .and_then(|names| names.get(slot))
+                    .filter(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("<slot {slot}>"));
+                (name, value.clone())
+            })
+            .collect()
     }
 
-    pub fn get(&self, name: &Token) -> Result<Value, RuntimeSignal> {
-        if let Some(value) = self.values.get(&name.lexeme) {
-            match value {
-                Value::Nil => {
-                    return Err(RuntimeSignal::runtime_error(
-                        name.clone(),
-                        format!(
-                            "Attempted to evaluate unitialized variable '{}'",
-                            name.lexeme
-                        ),
-                    ))
-                }
-                val => return Ok(val.clone()),
+    pub fn get_slot(&self, slot: usize) -> Option<Value> {
+        self.values.get(slot).cloned().flatten()
+    }
+
+    pub fn get_at(
+        env: &EnvRef,
+        distance: usize,
+        slot: usize,
+        name: &Token,
+    ) -> Result<Value, RuntimeSignal> {
+        Self::ancestor(env, distance)
+            .borrow()
+            .get_slot(slot)
+            .ok_or_else(|| uninitialized(name))
+    }
+
+    // the slot only exists once its declaration has run, which an assignment inside the
+    // variable's own initializer precedes
+    pub fn assign_at(
+        env: &EnvRef,
+        distance: usize,
+        slot: usize,
+        name: &Token,
+        value: &Value,
+    ) -> Result<(), RuntimeSignal> {
+        let env = Self::ancestor(env, distance);
+        match env.borrow_mut().values.get_mut(slot) {
+            Some(variable) => {
+                *variable = Some(value.clone());
+                Ok(())
             }
+            None => Err(RuntimeSignal::runtime_error(
+                name.clone(),
+                format!("invalid assignment target: {name}"),
+            )),
         }
+    }
 
-        match &self.enclosing {
-            Some(env) => env.borrow().get(name),
-            None => Err(RuntimeSignal::static_error(
-                name.line,
-                format!("Undefined Variable '{}'", name.lexeme),
-            )),
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        if let Some(enclosing) = &self.enclosing {
+            tracer.environment(enclosing);
         }
+        tracer.globals(&self.globals);
+        self.values.iter().flatten().for_each(|value| tracer.value(value));
     }
 
-    pub fn assign(&mut self, left: &Token, right: &Value) -> Result<(), RuntimeSignal> {
-        if let Some(key) = self.values.get_mut(&left.lexeme) {
-            *key = right.clone();
-            Ok(())
-        } else if let Some(env) = &mut self.enclosing {
-            env.borrow_mut().assign(left, right)
-        } else {
-            let err_msg = format!("invalid assignment target: {left}");
-            Err(RuntimeSignal::runtime_error(left.clone(), err_msg))
+    pub(super) fn take_values(&mut self) -> Vec<Option<Value>> {
+        std::mem::take(&mut self.values)
+    }
+
+    // walks 'distance' hops up the enclosing chain, as computed by the resolver
+    fn ancestor(env: &EnvRef, distance: usize) -> EnvRef {
+        let mut current = env.clone();
+        for _ in 0..distance {
+            let enclosing = current
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("resolved depth is deeper than the environment chain");
+            current = enclosing;
         }
+        current
     }
 }
+
+// reading a variable that was declared without an initializer and never assigned
+pub fn uninitialized(name: &Token) -> RuntimeSignal {
+    RuntimeSignal::runtime_error(
+        name.clone(),
+        format!("Attempted to evaluate unitialized variable '{}'", name.lexeme),
+    )
+}
@@ -0,0 +1,364 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    error::LoxError,
+    interpreter::{
+        callable::LoxCallable,
+        class::{LoxClass, LoxInstance},
+        environment::{EnvRef, Environment, Globals, GlobalsRef},
+        map::LoxMap,
+        values::Value,
+        vm::{Closure, Upvalue, UpvalueRef},
+    },
+};
+
+pub type HeapRef = Rc<RefCell<Heap>>;
+
+const DEFAULT_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapStats {
+    pub tracked: usize,   // live environments, global tables and upvalues
+    pub allocated: usize, // tracked since the last collection
+    pub threshold: usize, // allocations that trigger a collection
+    pub collections: usize,
+    pub freed: usize, // objects taken out of unreachable cycles, over all collections
+}
+
+// Rc frees everything except cycles, and every cycle runs through a scope or upvalue:
+// a closure stored in the environment it captured, directly or via instances and lists.
+// A collection finds the objects reachable from the tracked ones, and any whose strong
+// count is fully explained by references from within that graph can't be reached from
+// outside it. Those are cleared, which breaks the cycles and lets Rc free them.
+pub struct Heap {
+    environments: Vec<Weak<RefCell<Environment>>>,
+    globals: Vec<Weak<RefCell<Globals>>>,
+    upvalues: Vec<Weak<RefCell<Upvalue>>>,
+    allocated: usize,
+    threshold: usize,
+    collections: usize,
+    freed: usize,
+}
+
+impl Heap {
+    pub fn new_ref() -> HeapRef {
+        Rc::new(RefCell::new(Heap {
+            environments: Vec::new(),
+            globals: Vec::new(),
+            upvalues: Vec::new(),
+            allocated: 0,
+            threshold: DEFAULT_THRESHOLD,
+            collections: 0,
+            freed: 0,
+        }))
+    }
+
+    pub fn track_environment(&mut self, env: &EnvRef) {
+        self.environments.push(Rc::downgrade(env));
+        self.allocated += 1;
+    }
+
+    pub fn track_globals(&mut self, globals: &GlobalsRef) {
+        self.globals.push(Rc::downgrade(globals));
+        self.allocated += 1;
+    }
+
+    pub(super) fn track_upvalue(&mut self, upvalue: &UpvalueRef) {
+        self.upvalues.push(Rc::downgrade(upvalue));
+        self.allocated += 1;
+    }
+
+    pub fn set_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.allocated >= self.threshold
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let live = |count: usize| count > 0;
+        let tracked = self
+            .environments
+            .iter()
+            .filter(|env| live(env.strong_count()))
+            .count()
+            + self
+                .globals
+                .iter()
+                .filter(|globals| live(globals.strong_count()))
+                .count()
+            + self
+                .upvalues
+                .iter()
+                .filter(|upvalue| live(upvalue.strong_count()))
+                .count();
+
+        HeapStats {
+            tracked,
+            allocated: self.allocated,
+            threshold: self.threshold,
+            collections: self.collections,
+            freed: self.freed,
+        }
+    }
+
+    // returns how many unreachable objects were cleared
+    pub fn collect(&mut self) -> usize {
+        self.environments.retain(|env| env.strong_count() > 0);
+        self.globals.retain(|globals| globals.strong_count() > 0);
+        self.upvalues.retain(|upvalue| upvalue.strong_count() > 0);
+
+        let mut graph = Graph::default();
+        for env in self.environments.iter().filter_map(Weak::upgrade) {
+            graph.add(Node::Environment(env));
+        }
+        for globals in self.globals.iter().filter_map(Weak::upgrade) {
+            graph.add(Node::Globals(globals));
+        }
+        for upvalue in self.upvalues.iter().filter_map(Weak::upgrade) {
+            graph.add(Node::Upvalue(upvalue));
+        }
+        graph.trace();
+
+        let garbage = graph.unreachable();
+        for &node in &garbage {
+            graph.nodes[node].clear();
+        }
+        drop(graph);
+
+        self.allocated = 0;
+        self.collections += 1;
+        self.freed += garbage.len();
+        self.environments.retain(|env| env.strong_count() > 0);
+        self.globals.retain(|globals| globals.strong_count() > 0);
+        self.upvalues.retain(|upvalue| upvalue.strong_count() > 0);
+        garbage.len()
+    }
+}
+
+// collects the objects another one holds a strong reference to
+#[derive(Default)]
+pub struct Tracer {
+    nodes: Vec<Node>,
+}
+
+impl Tracer {
+    pub fn value(&mut self, value: &Value) {
+        let node = match value {
+            Value::Callable(callable) => Node::Callable(callable.clone()),
+            Value::Class(class) => return self.class(class),
+            Value::Instance(instance) => Node::Instance(instance.clone()),
+            Value::List(list) => Node::List(list.clone()),
+            Value::Map(map) => Node::Map(map.clone()),
+            Value::Error(err) => Node::Error(err.clone()),
+            Value::Nil | Value::Boolean(_) | Value::Number(_) | Value::String(_) => return,
+        };
+        self.nodes.push(node);
+    }
+
+    pub fn environment(&mut self, env: &EnvRef) {
+        self.nodes.push(Node::Environment(env.clone()));
+    }
+
+    pub fn globals(&mut self, globals: &GlobalsRef) {
+        self.nodes.push(Node::Globals(globals.clone()));
+    }
+
+    pub(super) fn upvalue(&mut self, upvalue: &UpvalueRef) {
+        self.nodes.push(Node::Upvalue(upvalue.clone()));
+    }
+
+    pub fn class(&mut self, class: &Rc<LoxClass>) {
+        self.nodes.push(Node::Class(class.clone()));
+    }
+
+    // what a callable holds; class methods are stored inline rather than behind an Rc
+    pub fn callable(&mut self, callable: &LoxCallable) {
+        match callable {
+            // whatever a host closure captured is invisible, so it counts as external
+            LoxCallable::Native { .. } => {}
+            LoxCallable::LoxFunction { closure, .. } => self.environment(closure),
+            LoxCallable::Compiled { closure, receiver } => {
+                self.nodes.push(Node::Closure(closure.clone()));
+                if let Some(receiver) = receiver {
+                    self.value(receiver);
+                }
+            }
+        }
+    }
+}
+
+enum Node {
+    Environment(EnvRef),
+    Globals(GlobalsRef),
+    Upvalue(UpvalueRef),
+    Callable(Rc<LoxCallable>),
+    Closure(Rc<Closure>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Error(Rc<LoxError>),
+}
+
+impl Node {
+    fn address(&self) -> usize {
+        match self {
+            Node::Environment(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Globals(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Upvalue(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Callable(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Closure(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Class(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Instance(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::List(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Map(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Error(rc) => Rc::as_ptr(rc) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Environment(rc) => Rc::strong_count(rc),
+            Node::Globals(rc) => Rc::strong_count(rc),
+            Node::Upvalue(rc) => Rc::strong_count(rc),
+            Node::Callable(rc) => Rc::strong_count(rc),
+            Node::Closure(rc) => Rc::strong_count(rc),
+            Node::Class(rc) => Rc::strong_count(rc),
+            Node::Instance(rc) => Rc::strong_count(rc),
+            Node::List(rc) => Rc::strong_count(rc),
+            Node::Map(rc) => Rc::strong_count(rc),
+            Node::Error(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    // false if the object is borrowed right now, i.e. in use and certainly reachable
+    fn trace(&self, tracer: &mut Tracer) -> bool {
+        match self {
+            Node::Environment(env) => env.try_borrow().map(|env| env.trace(tracer)).is_ok(),
+            Node::Globals(globals) => globals
+                .try_borrow()
+                .map(|globals| globals.trace(tracer))
+                .is_ok(),
+            Node::Upvalue(upvalue) => upvalue
+                .try_borrow()
+                .map(|upvalue| upvalue.trace(tracer))
+                .is_ok(),
+            Node::Callable(callable) => {
+                tracer.callable(callable);
+                true
+            }
+            Node::Closure(closure) => {
+                closure.trace(tracer);
+                true
+            }
+            Node::Class(class) => {
+                class.trace(tracer);
+                true
+            }
+            Node::Instance(instance) => instance
+                .try_borrow()
+                .map(|instance| instance.trace(tracer))
+                .is_ok(),
+            Node::List(list) => list
+                .try_borrow()
+                .map(|list| list.iter().for_each(|value| tracer.value(value)))
+                .is_ok(),
+            Node::Map(map) => map
+                .try_borrow()
+                .map(|map| map.iter().for_each(|(_, value)| tracer.value(value)))
+                .is_ok(),
+            Node::Error(err) => {
+                if let Some(thrown) = err.thrown() {
+                    tracer.value(thrown);
+                }
+                true
+            }
+        }
+    }
+
+    // drops what an unreachable object holds; the contents are moved out first so
+    // no borrow is held while they are freed
+    fn clear(&self) {
+        match self {
+            Node::Environment(env) => drop(env.borrow_mut().take_values()),
+            Node::Globals(globals) => drop(globals.borrow_mut().take_values()),
+            Node::Upvalue(upvalue) => drop(upvalue.replace(Upvalue::Closed(None))),
+            Node::Instance(instance) => drop(instance.borrow_mut().take_fields()),
+            Node::List(list) => drop(list.take()),
+            Node::Map(map) => drop(map.take()),
+            // immutable once built; clearing what they point to is enough
+            Node::Callable(_) | Node::Closure(_) | Node::Class(_) | Node::Error(_) => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<usize, usize>, // address -> node
+    edges: Vec<Vec<usize>>,
+    opaque: Vec<bool>, // couldn't be traced, so kept alive
+}
+
+impl Graph {
+    fn add(&mut self, node: Node) -> usize {
+        let address = node.address();
+        if let Some(&existing) = self.index.get(&address) {
+            return existing;
+        }
+        self.nodes.push(node);
+        self.index.insert(address, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    // walks everything reachable from the nodes added so far
+    fn trace(&mut self) {
+        let mut next = 0;
+        while next < self.nodes.len() {
+            let mut tracer = Tracer::default();
+            let traced = self.nodes[next].trace(&mut tracer);
+            let edges = tracer
+                .nodes
+                .into_iter()
+                .map(|node| self.add(node))
+                .collect();
+            self.edges.push(edges);
+            self.opaque.push(!traced);
+            next += 1;
+        }
+    }
+
+    fn unreachable(&self) -> Vec<usize> {
+        let mut internal = vec![0; self.nodes.len()];
+        for edges in &self.edges {
+            for &target in edges {
+                internal[target] += 1;
+            }
+        }
+
+        // the graph itself holds one reference to every node
+        let mut reachable: Vec<bool> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| self.opaque[i] || node.strong_count() - 1 > internal[i])
+            .collect();
+        let mut pending: Vec<usize> = (0..self.nodes.len()).filter(|&i| reachable[i]).collect();
+        while let Some(node) = pending.pop() {
+            for &target in &self.edges[node] {
+                if !reachable[target] {
+                    reachable[target] = true;
+                    pending.push(target);
+                }
+            }
+        }
+
+        (0..self.nodes.len()).filter(|&i| !reachable[i]).collect()
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::error::{ErrorKind, RuntimeSignal};
+
+// reading the clock on every statement would dominate tight loops
+const CLOCK_CHECK_INTERVAL: u64 = 256;
+
+// bounds on a single run, for scripts that can't be trusted to finish; checked before every
+// statement, and none of the errors can be caught by the script
+#[derive(Default)]
+pub struct Limits {
+    statement_budget: Option<u64>,
+    time_limit: Option<Duration>,
+    interrupt: Arc<AtomicBool>,
+    statements: u64, // run so far
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    pub fn set_statement_budget(&mut self, budget: Option<u64>) {
+        self.statement_budget = budget;
+    }
+
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.time_limit = limit;
+    }
+
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    // budgets and deadlines count from the start of each top-level run
+    pub fn start(&mut self) {
+        self.statements = 0;
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+    }
+
+    // the limits stay exceeded once hit, so 'finally' blocks can't keep a script running
+    pub fn check(&mut self) -> Result<(), RuntimeSignal> {
+        self.statements += 1;
+
+        if let Some(budget) = self.statement_budget
+            && self.statements > budget
+        {
+            return Err(RuntimeSignal::limit_error(
+                ErrorKind::BudgetExhausted,
+                format!("statement budget of {budget} exhausted"),
+            ));
+        }
+
+        if self.interrupt.load(Ordering::Relaxed) {
+            return Err(RuntimeSignal::limit_error(
+                ErrorKind::Interrupted,
+                "execution interrupted".to_string(),
+            ));
+        }
+
+        if let (Some(deadline), Some(limit)) = (self.deadline, self.time_limit)
+            && self.statements.is_multiple_of(CLOCK_CHECK_INTERVAL)
+            && Instant::now() >= deadline
+        {
+            return Err(RuntimeSignal::limit_error(
+                ErrorKind::DeadlineExceeded,
+                format!("time limit of {limit:?} exceeded"),
+            ));
+        }
+
+        Ok(())
+    }
+}
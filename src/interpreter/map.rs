@@ -0,0 +1,87 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::interpreter::values::Value;
+
+// the subset of values usable as map keys; numbers hash by their bits, with -0.0
+// folded into 0.0 so keys agree with '=='. NaN is never equal to itself, so it is
+// rejected rather than creating entries that can't be looked up again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Nil,
+    Boolean(bool),
+    Number(u64),
+    String(Rc<String>),
+}
+
+impl HashKey {
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Nil => Ok(HashKey::Nil),
+            Value::Boolean(b) => Ok(HashKey::Boolean(*b)),
+            Value::Number(n) if n.is_nan() => Err("NaN can't be used as a map key".into()),
+            Value::Number(n) if *n == 0.0 => Ok(HashKey::Number(0f64.to_bits())),
+            Value::Number(n) => Ok(HashKey::Number(n.to_bits())),
+            Value::String(s) => Ok(HashKey::String(s.clone())),
+            other => Err(format!("{other} can't be used as a map key")),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            HashKey::Nil => Value::Nil,
+            HashKey::Boolean(b) => Value::Boolean(*b),
+            HashKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            HashKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+// insertion ordered, so keys() and printing are deterministic
+#[derive(Debug, Default)]
+pub struct LoxMap {
+    entries: Vec<(HashKey, Value)>,
+    index: HashMap<HashKey, usize>,
+}
+
+impl LoxMap {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &HashKey) -> Option<&Value> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains(&self, key: &HashKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: HashKey, value: Value) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &HashKey) -> Option<Value> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            if let Some(position) = self.index.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&HashKey, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
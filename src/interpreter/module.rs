@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    compiler::ScriptMode,
+    error::{LoxError, RuntimeSignal},
+    interpreter::{
+        callable::LoxCallable,
+        environment::{Environment, Globals, GlobalsRef},
+        stmt::{ImportStatement, Stmt},
+        values::Value,
+        Backend, Interpreter,
+    },
+    lox,
+    scanner::token::Token,
+};
+
+type Exports = Rc<HashMap<String, Value>>;
+
+// every module is run once; later imports of the same file share its exports
+#[derive(Default)]
+pub struct Modules {
+    loaded: HashMap<PathBuf, Exports>,
+    loading: Vec<PathBuf>, // the chain of files currently running, outermost first
+    current: Option<PathBuf>,
+    exported: Vec<String>,
+}
+
+impl Modules {
+    // the main script counts as loading, so importing it back is reported as a cycle
+    pub fn set_current(&mut self, path: PathBuf) {
+        self.loading = fs::canonicalize(&path).into_iter().collect();
+        self.current = Some(path);
+    }
+
+    pub fn export(&mut self, name: String) {
+        self.exported.push(name);
+    }
+
+    // relative to the importing file, or the working directory outside of one
+    fn locate(&self, specifier: &str) -> PathBuf {
+        let base = match &self.current {
+            Some(current) => current.parent().unwrap_or(Path::new("")).to_path_buf(),
+            None => PathBuf::new(),
+        };
+        let mut path = base.join(specifier);
+        if path.extension().is_none() {
+            path.set_extension("lox");
+        }
+        path
+    }
+}
+
+impl Interpreter {
+    pub(super) fn execute_import(&mut self, import: &ImportStatement) -> Result<(), RuntimeSignal> {
+        let bindings = self.import_bindings(import)?;
+        let globals = self.current_globals();
+        for (name, value) in bindings {
+            globals.borrow_mut().define(name, value);
+        }
+        Ok(())
+    }
+
+    // the names an import statement binds in the importing file's globals
+    pub(super) fn import_bindings(
+        &mut self,
+        import: &ImportStatement,
+    ) -> Result<Vec<(String, Value)>, RuntimeSignal> {
+        let specifier = import.path.lexeme.trim_matches('"');
+        let exports = self.load_module(&import.path, specifier)?;
+
+        match &import.names {
+            None => Ok(exports
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()),
+            Some(names) => names
+                .iter()
+                .map(|name| match exports.get(&name.lexeme) {
+                    Some(value) => Ok((name.lexeme.clone(), value.clone())),
+                    None => Err(RuntimeSignal::runtime_error(
+                        name.clone(),
+                        format!("Module '{specifier}' has no export '{}'", name.lexeme),
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    fn load_module(&mut self, path_token: &Token, specifier: &str) -> Result<Exports, RuntimeSignal> {
+        let located = self.modules.locate(specifier);
+        let path = fs::canonicalize(&located).map_err(|err| {
+            RuntimeSignal::runtime_error(
+                path_token.clone(),
+                format!("Could not load module '{}': {err}", located.display()),
+            )
+        })?;
+
+        if let Some(exports) = self.modules.loaded.get(&path) {
+            return Ok(exports.clone());
+        }
+
+        if let Some(start) = self.modules.loading.iter().position(|loading| *loading == path) {
+            let cycle: Vec<String> = self.modules.loading[start..]
+                .iter()
+                .chain([&path])
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(RuntimeSignal::runtime_error(
+                path_token.clone(),
+                format!("Cyclic import: {}", cycle.join(" -> ")),
+            ));
+        }
+
+        let source = fs::read_to_string(&path).map_err(|err| {
+            RuntimeSignal::runtime_error(
+                path_token.clone(),
+                format!("Could not load module '{}': {err}", path.display()),
+            )
+        })?;
+
+        let statements = lox::compile(self, &source).map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(describe).collect();
+            module_error(path_token, specifier, &messages.join("; "))
+        })?;
+
+        // run the module's top level against fresh globals, then put everything back
+        let globals = self.module_globals();
+        let previous_current = self.modules.current.replace(path.clone());
+        let previous_exported = std::mem::take(&mut self.modules.exported);
+        self.modules.loading.push(path.clone());
+
+        let result = self.run_module(&statements, globals.clone());
+
+        self.modules.loading.pop();
+        let exported = std::mem::replace(&mut self.modules.exported, previous_exported);
+        self.modules.current = previous_current;
+
+        if let Err(signal) = result {
+            return Err(module_error(
+                path_token,
+                specifier,
+                &describe(&signal.into_error()),
+            ));
+        }
+
+        let globals = globals.borrow();
+        let exports: Exports = Rc::new(
+            exported
+                .into_iter()
+                .filter_map(|name| globals.get_own(&name).map(|value| (name, value)))
+                .collect(),
+        );
+        self.modules.loaded.insert(path, exports.clone());
+        Ok(exports)
+    }
+
+    fn run_module(&mut self, statements: &[Stmt], globals: GlobalsRef) -> Result<(), RuntimeSignal> {
+        match self.backend {
+            Backend::TreeWalk => {
+                let top_level = Environment::top_level(globals, self.heap.clone());
+                let previous_env = std::mem::replace(&mut self.environment, top_level);
+                let result = statements
+                    .iter()
+                    .try_for_each(|stmt| self.evaluate_statement(stmt));
+                self.environment = previous_env;
+                result
+            }
+            Backend::Bytecode => self
+                .run_compiled(statements, ScriptMode::Run, globals)
+                .map(drop),
+        }
+    }
+
+    // a module sees the host's natives but none of the importer's globals
+    fn module_globals(&self) -> GlobalsRef {
+        let globals = Globals::new_ref();
+        self.heap.borrow_mut().track_globals(&globals);
+        for (name, value) in self.globals.borrow().bindings() {
+            if let Value::Callable(callable) = value
+                && matches!(**callable, LoxCallable::Native { .. })
+            {
+                globals.borrow_mut().define(name.clone(), value.clone());
+            }
+        }
+        globals
+    }
+}
+
+fn describe(err: &LoxError) -> String {
+    if err.line() == 0 {
+        err.message().to_string()
+    } else {
+        format!("[line {}] {}", err.line(), err.message())
+    }
+}
+
+// errors inside a module are reported at the import, since they can't be rendered
+// against the importing file's source
+fn module_error(path_token: &Token, specifier: &str, message: &str) -> RuntimeSignal {
+    RuntimeSignal::runtime_error(
+        path_token.clone(),
+        format!("Error in module '{specifier}': {message}"),
+    )
+}
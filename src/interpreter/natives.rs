@@ -0,0 +1,153 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    error::RuntimeSignal,
+    interpreter::{
+        callable::Arity,
+        map::{HashKey, LoxMap},
+        values::Value,
+        Interpreter,
+    },
+};
+
+type ListRef = Rc<RefCell<Vec<Value>>>;
+type MapRef = Rc<RefCell<LoxMap>>;
+
+pub fn define_list_natives(interpreter: &mut Interpreter) {
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("push", 2, push);
+    interpreter.define_native("pop", 1, pop);
+    interpreter.define_native("insert", 3, insert);
+    interpreter.define_native("slice", Arity::AtLeast(2), slice);
+}
+
+pub fn define_map_natives(interpreter: &mut Interpreter) {
+    interpreter.define_native("keys", 1, keys);
+    interpreter.define_native("values", 1, values);
+    interpreter.define_native("has", 2, has);
+    interpreter.define_native("remove", 2, remove);
+}
+
+// len(list or map)
+fn len(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    match &args[0] {
+        Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+        other => Err(RuntimeSignal::native_error(format!(
+            "len() expects a list or map, got {other}"
+        ))),
+    }
+}
+
+// push(list, value): appends and returns the new length
+fn push(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let list = expect_list("push", &args[0])?;
+    list.borrow_mut().push(args[1].clone());
+    let len = list.borrow().len();
+    Ok(Value::Number(len as f64))
+}
+
+// pop(list): removes and returns the last element
+fn pop(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let list = expect_list("pop", &args[0])?;
+    let popped = list.borrow_mut().pop();
+    popped.ok_or_else(|| RuntimeSignal::native_error("pop() called on an empty list".into()))
+}
+
+// insert(list, index, value): index may equal the length to append
+fn insert(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let list = expect_list("insert", &args[0])?;
+    let len = list.borrow().len();
+    let index = expect_position("insert", &args[1], len)?;
+    list.borrow_mut().insert(index, args[2].clone());
+    Ok(Value::Nil)
+}
+
+// slice(list, start, end?): a new list of the elements in [start, end)
+fn slice(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    if args.len() > 3 {
+        return Err(RuntimeSignal::native_error(format!(
+            "slice() expects 2 or 3 arguments, but got {}",
+            args.len()
+        )));
+    }
+
+    let list = expect_list("slice", &args[0])?;
+    let list = list.borrow();
+    let start = expect_position("slice", &args[1], list.len())?;
+    let end = match args.get(2) {
+        Some(end) => expect_position("slice", end, list.len())?,
+        None => list.len(),
+    };
+
+    if start > end {
+        return Err(RuntimeSignal::native_error(format!(
+            "slice() start {start} is after end {end}"
+        )));
+    }
+
+    Ok(Value::list(list[start..end].to_vec()))
+}
+
+// keys(map): a list of the keys in insertion order
+fn keys(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let map = expect_map("keys", &args[0])?;
+    let keys = map.borrow().iter().map(|(key, _)| key.to_value()).collect();
+    Ok(Value::list(keys))
+}
+
+// values(map): a list of the values in insertion order
+fn values(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let map = expect_map("values", &args[0])?;
+    let values = map.borrow().iter().map(|(_, value)| value.clone()).collect();
+    Ok(Value::list(values))
+}
+
+// has(map, key)
+fn has(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let map = expect_map("has", &args[0])?;
+    let key = expect_key("has", &args[1])?;
+    let found = map.borrow().contains(&key);
+    Ok(Value::Boolean(found))
+}
+
+// remove(map, key): returns the removed value, or nil if the key was absent
+fn remove(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeSignal> {
+    let map = expect_map("remove", &args[0])?;
+    let key = expect_key("remove", &args[1])?;
+    let removed = map.borrow_mut().remove(&key);
+    Ok(removed.unwrap_or(Value::Nil))
+}
+
+fn expect_map(function: &str, value: &Value) -> Result<MapRef, RuntimeSignal> {
+    match value {
+        Value::Map(map) => Ok(map.clone()),
+        other => Err(RuntimeSignal::native_error(format!(
+            "{function}() expects a map, got {other}"
+        ))),
+    }
+}
+
+fn expect_key(function: &str, value: &Value) -> Result<HashKey, RuntimeSignal> {
+    HashKey::from_value(value)
+        .map_err(|message| RuntimeSignal::native_error(format!("{function}(): {message}")))
+}
+
+fn expect_list(function: &str, value: &Value) -> Result<ListRef, RuntimeSignal> {
+    match value {
+        Value::List(list) => Ok(list.clone()),
+        other => Err(RuntimeSignal::native_error(format!(
+            "{function}() expects a list, got {other}"
+        ))),
+    }
+}
+
+// a whole number in 0..=len
+fn expect_position(function: &str, value: &Value, len: usize) -> Result<usize, RuntimeSignal> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n as usize <= len => Ok(*n as usize),
+        other => Err(RuntimeSignal::native_error(format!(
+            "{function}() position {other} out of range for length {len}"
+        ))),
+    }
+}
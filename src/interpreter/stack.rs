@@ -0,0 +1,58 @@
+use std::{
+    cell::Cell,
+    io,
+    thread::{self, JoinHandle},
+};
+
+use crate::interpreter::Interpreter;
+
+// what a thread not started by 'spawn_with_stack' is assumed to have: the size rust gives
+// spawned threads, and less than a main thread usually gets
+const ASSUMED_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+thread_local! {
+    static STACK_SIZE: Cell<usize> = const { Cell::new(ASSUMED_STACK_SIZE) };
+}
+
+// starts a thread with 'size' bytes of stack that interpreters running on it know they can
+// use; the tree-walker recurses natively for every lox call (~25-90KB a call in debug builds,
+// depending on how deeply the body nests), so deep recursion needs more than a default thread
+pub fn spawn_with_stack<F, T>(size: usize, run: F) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new().stack_size(size).spawn(move || {
+        STACK_SIZE.with(|stack_size| stack_size.set(size));
+        run()
+    })
+}
+
+impl Interpreter {
+    // runs 'run' with the stack guard measuring from here, unless a run is already underway,
+    // e.g. for a module imported while a script runs
+    pub(super) fn with_stack_guard<T>(&mut self, run: impl FnOnce(&mut Self) -> T) -> T {
+        if self.stack_base.is_some() {
+            return run(self);
+        }
+
+        let base = 0u8;
+        self.stack_base = Some(stack_address(&base));
+        let result = run(self);
+        self.stack_base = None;
+        result
+    }
+
+    // whether a run has used half of its thread's stack; the other half is left for whatever
+    // the host had on the stack before the run, and for the nesting within the last call
+    pub(super) fn stack_exhausted(&self) -> bool {
+        let here = 0u8;
+        let usable = STACK_SIZE.with(Cell::get) / 2;
+        self.stack_base
+            .is_some_and(|base| base.abs_diff(stack_address(&here)) > usable)
+    }
+}
+
+fn stack_address(local: &u8) -> usize {
+    local as *const u8 as usize
+}
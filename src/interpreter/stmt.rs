@@ -5,13 +5,20 @@ use crate::{ast::expression::Expr, scanner::token::Token};
 #[derive(Debug)]
 pub enum Stmt {
     Block(Vec<Stmt>),
+    Break(Token, Option<Token>), // keyword, target label
+    Class(ClassDefinition),
+    Continue(Token, Option<Token>),
+    Export(Box<Stmt>), // a var, fun or class declaration visible to importers
     Expression(Expr),
     Function(Rc<FunctionDefinition>),
     If(IfConditions),
-    Print(Expr),
+    Import(ImportStatement),
+    Print(Token, Expr), // keyword, value
     Var(Token, Option<Expr>), // variables can be delcared unitialized
     While(WhileConditions),
     Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    Try(TryStatement),
 }
 
 #[derive(Debug)]
@@ -21,43 +28,133 @@ pub struct FunctionDefinition {
     pub body: Vec<Stmt>,
 }
 
+impl FunctionDefinition {
+    pub fn new(name: Token, params: Vec<Token>, body: Stmt) -> Self {
+        let body = if let Stmt::Block(block) = body {
+            block
+        } else {
+            panic!("body for func {} is not a block statement!", name)
+        };
+
+        FunctionDefinition { name, params, body }
+    }
+}
+
+// import "path"; brings in every export, import { a, b } from "path"; only the names listed
+#[derive(Debug, Clone)]
+pub struct ImportStatement {
+    pub keyword: Token,
+    pub path: Token,
+    pub names: Option<Vec<Token>>,
+}
+
+#[derive(Debug)]
+pub struct TryStatement {
+    pub keyword: Token,
+    pub body: Vec<Stmt>,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<Vec<Stmt>>,
+}
+
+// catch (name) { body }
+#[derive(Debug)]
+pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+pub struct ClassDefinition {
+    pub name: Token,
+    pub methods: Vec<Rc<FunctionDefinition>>,
+}
+
 #[derive(Debug)]
 pub struct WhileConditions {
+    pub keyword: Token, // 'while', or 'for' for a desugared for loop
+    pub label: Option<Token>,
     pub condition: Expr,
     pub stmt_body: Box<Stmt>,
+    pub increment: Option<Expr>, // a desugared for loop's increment, run even after 'continue'
 }
 
 #[derive(Debug)]
 pub struct IfConditions {
+    pub keyword: Token,
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
 }
 
 impl Stmt {
-    pub fn function(name: Token, params: Vec<Token>, body: Stmt) -> Self {
-        let fun_body = if let Stmt::Block(block) = body {
-            block
-        } else {
-            panic!("body for func {} is not a block statement!", name)
-        };
+    // the name bound by a declaration statement
+    pub fn declared_name(&self) -> Option<&Token> {
+        match self {
+            Stmt::Var(name, _) => Some(name),
+            Stmt::Function(fun_def) => Some(&fun_def.name),
+            Stmt::Class(class_def) => Some(&class_def.name),
+            _ => None,
+        }
+    }
+
+    // the line a statement starts on; blocks have none of their own, and neither does an
+    // expression statement with no tokens in it, such as a bare literal
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Block(_) => None,
+            Stmt::Break(keyword, _)
+            | Stmt::Continue(keyword, _)
+            | Stmt::Print(keyword, _)
+            | Stmt::Return(keyword, _)
+            | Stmt::Throw(keyword, _) => Some(keyword.line),
+            Stmt::Class(class_def) => Some(class_def.name.line),
+            Stmt::Function(fun_def) => Some(fun_def.name.line),
+            Stmt::Var(name, _) => Some(name.line),
+            Stmt::Export(declaration) => declaration.line(),
+            Stmt::Expression(expr) => expr.line(),
+            Stmt::If(conditions) => Some(conditions.keyword.line),
+            Stmt::While(conditions) => Some(conditions.keyword.line),
+            Stmt::Import(import) => Some(import.keyword.line),
+            Stmt::Try(try_stmt) => Some(try_stmt.keyword.line),
+        }
+    }
+
+    pub fn class(name: Token, methods: Vec<Stmt>) -> Self {
+        let methods = methods
+            .into_iter()
+            .map(|method| match method {
+                Stmt::Function(fun_def) => fun_def,
+                _ => panic!("method in class {} is not a function statement!", name),
+            })
+            .collect();
 
-        Stmt::Function(Rc::new(FunctionDefinition {
-            name,
-            params,
-            body: fun_body,
-        }))
+        Stmt::Class(ClassDefinition { name, methods })
     }
 
-    pub fn while_statement(condition: Expr, stmt_body: Stmt) -> Self {
+    pub fn while_statement(
+        keyword: Token,
+        label: Option<Token>,
+        condition: Expr,
+        stmt_body: Stmt,
+        increment: Option<Expr>,
+    ) -> Self {
         Stmt::While(WhileConditions {
+            keyword,
+            label,
             condition,
             stmt_body: Box::new(stmt_body),
+            increment,
         })
     }
 
-    pub fn if_statement(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Self {
+    pub fn if_statement(
+        keyword: Token,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Self {
         Stmt::If(IfConditions {
+            keyword,
             condition,
             then_branch: Box::new(then_branch),
             else_branch: else_branch.map(Box::new),
@@ -1,6 +1,13 @@
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
 
-use crate::interpreter::callable::LoxCallable;
+use crate::{
+    error::LoxError,
+    interpreter::{
+        callable::LoxCallable,
+        class::{LoxClass, LoxInstance},
+        map::LoxMap,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -9,21 +16,80 @@ pub enum Value {
     Number(f64),
     String(Rc<String>),
     Callable(Rc<LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Error(Rc<LoxError>), // a caught runtime error
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_nested(f, &mut Vec::new())
+    }
+}
+
+impl Value {
+    pub fn list(elements: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(map: LoxMap) -> Self {
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+
+    // 'open' holds the lists and maps being printed around this value; one that contains itself
+    // prints as '[...]' or '{...}' where it repeats instead of recursing until the stack runs out
+    fn write_nested(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        open: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
         match self {
             Value::Nil => write!(f, "nil"),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Callable(_c) => write!(f, "callable"),
+            Value::Class(class) => write!(f, "{}", class.name),
+            Value::Instance(instance) => write!(f, "{} instance", instance.borrow().class_name()),
+            Value::List(list) => {
+                let id = Rc::as_ptr(list) as *const ();
+                if open.contains(&id) {
+                    return write!(f, "[...]");
+                }
+                open.push(id);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write_nested(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let id = Rc::as_ptr(map) as *const ();
+                if open.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+                open.push(id);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key.to_value())?;
+                    value.write_nested(f, open)?;
+                }
+                open.pop();
+                write!(f, "}}")
+            }
+            Value::Error(err) => write!(f, "<error: {}>", err.message()),
         }
     }
-}
 
-impl Value {
     pub fn is_truthy(&self) -> bool {
         match *self {
             Value::Nil => false,
@@ -54,6 +120,9 @@ impl Value {
             Value::Number(n) => n.to_string(),
             Value::String(s) => s.to_string(),
             Value::Callable(_) => "callabe".into(),
+            Value::Class(class) => class.name.clone(),
+            Value::Instance(instance) => format!("{} instance", instance.borrow().class_name()),
+            Value::List(_) | Value::Map(_) | Value::Error(_) => self.to_string(),
         }
     }
 }
@@ -66,6 +135,11 @@ impl PartialEq for Value {
             (Value::Number(a), Value::Number(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Callable(a), Value::Callable(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Error(a), Value::Error(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -0,0 +1,622 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    compiler::{
+        self,
+        chunk::{Function, Op},
+        ScriptMode,
+    },
+    error::{CallFrame, ErrorKind, LoxError, RuntimeSignal},
+    interpreter::{
+        binary_op,
+        callable::LoxCallable,
+        caught_value, check_call,
+        class::{LoxClass, LoxInstance},
+        environment::{uninitialized, GlobalsRef},
+        expect_instance, get_property,
+        heap::{HeapRef, Tracer},
+        index_get, index_set,
+        map::{HashKey, LoxMap},
+        map_key,
+        stmt::Stmt,
+        throw_value, unary_op,
+        values::Value,
+        Interpreter,
+    },
+    scanner::token::Token,
+};
+
+// a compiled function with the variables it closed over and the globals of its file
+pub struct Closure {
+    function: Rc<Function>,
+    upvalues: Vec<UpvalueRef>,
+    globals: GlobalsRef,
+}
+
+impl Closure {
+    pub fn function(&self) -> &Function {
+        &self.function
+    }
+
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        self.upvalues.iter().for_each(|upvalue| tracer.upvalue(upvalue));
+        tracer.globals(&self.globals);
+    }
+}
+
+pub(super) type UpvalueRef = Rc<RefCell<Upvalue>>;
+
+// open while the captured variable still lives on the stack, closed once its scope ends
+pub(super) enum Upvalue {
+    Open(usize),
+    Closed(Option<Value>),
+}
+
+impl Upvalue {
+    // an open upvalue's value is on the stack, which the collector can't see into
+    pub(super) fn trace(&self, tracer: &mut Tracer) {
+        if let Upvalue::Closed(Some(value)) = self {
+            tracer.value(value);
+        }
+    }
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    call_site: Option<u32>, // in the caller's chunk; None for frames entered from the host
+}
+
+// an active try statement: where to resume and what to unwind to
+struct Handler {
+    frame: usize,
+    stack: usize,
+    call_stack: usize,
+    target: usize,
+    catch: bool,
+}
+
+// stack slots hold None for variables declared without an initializer
+#[derive(Default)]
+pub struct Vm {
+    stack: Vec<Option<Value>>,
+    frames: Vec<Frame>,
+    handlers: Vec<Handler>,
+    open_upvalues: Vec<UpvalueRef>,
+}
+
+impl Vm {
+    fn push(&mut self, value: Value) {
+        self.stack.push(Some(value));
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .flatten()
+            .expect("operands are always initialized")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack
+            .last()
+            .and_then(Option::as_ref)
+            .expect("operands are always initialized")
+    }
+
+    fn pop_many(&mut self, count: usize) -> Vec<Value> {
+        let start = self.stack.len() - count;
+        self.stack
+            .split_off(start)
+            .into_iter()
+            .map(|value| value.expect("operands are always initialized"))
+            .collect()
+    }
+
+    fn capture_upvalue(&mut self, slot: usize, heap: &HeapRef) -> UpvalueRef {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot));
+        if let Some(existing) = existing {
+            return existing.clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        heap.borrow_mut().track_upvalue(&upvalue);
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    // moves the values of slots at or above 'from' into the closures that captured them
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let mut upvalue = upvalue.borrow_mut();
+            match *upvalue {
+                Upvalue::Open(slot) if slot >= from => {
+                    *upvalue = Upvalue::Closed(stack[slot].clone());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+}
+
+impl Interpreter {
+    pub(super) fn run_compiled(
+        &mut self,
+        statements: &[Stmt],
+        mode: ScriptMode,
+        globals: GlobalsRef,
+    ) -> Result<Value, RuntimeSignal> {
+        let function = compiler::compile(statements, mode)?;
+        let closure = Rc::new(Closure {
+            function,
+            upvalues: Vec::new(),
+            globals,
+        });
+        self.enter(closure, Value::Nil, Vec::new())
+    }
+
+    // calls from the host, e.g. a native calling back into lox
+    pub(super) fn call_closure(
+        &mut self,
+        closure: &Rc<Closure>,
+        receiver: Option<Value>,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeSignal> {
+        self.enter(closure.clone(), receiver.unwrap_or(Value::Nil), args)
+    }
+
+    fn enter(
+        &mut self,
+        closure: Rc<Closure>,
+        receiver: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeSignal> {
+        let entry = self.vm.frames.len();
+        let base = self.vm.stack.len();
+        self.vm.push(receiver);
+        self.vm.stack.extend(args.into_iter().map(Some));
+        self.vm.frames.push(Frame {
+            closure,
+            ip: 0,
+            base,
+            call_site: None,
+        });
+        self.execute(entry)
+    }
+
+    // runs until the frame at 'entry' returns; errors not handled above it propagate
+    fn execute(&mut self, entry: usize) -> Result<Value, RuntimeSignal> {
+        let call_depth = self.call_stack.len();
+        loop {
+            let op = self.next_op();
+            match self.step(op) {
+                Ok(None) => {}
+                Ok(Some(value)) if self.vm.frames.len() == entry => return Ok(value),
+                Ok(Some(value)) => self.vm.push(value),
+                Err(signal) => self.unwind(signal, entry, call_depth)?,
+            }
+        }
+    }
+
+    fn next_op(&mut self) -> Op {
+        let frame = self.vm.frames.last_mut().expect("a frame is running");
+        let op = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        op
+    }
+
+    fn frame(&self) -> &Frame {
+        self.vm.frames.last().expect("a frame is running")
+    }
+
+    fn token(&self, index: u32) -> &Token {
+        &self.frame().closure.function.chunk.tokens[index as usize]
+    }
+
+    // executes one instruction, returning a value when it returns from a frame
+    fn step(&mut self, op: Op) -> Result<Option<Value>, RuntimeSignal> {
+        match op {
+            Op::Constant(index) => {
+                let value = self.frame().closure.function.chunk.constants[index as usize].clone();
+                self.vm.push(value);
+            }
+            Op::Nil => self.vm.push(Value::Nil),
+            Op::True => self.vm.push(Value::Boolean(true)),
+            Op::False => self.vm.push(Value::Boolean(false)),
+            Op::Uninitialized => self.vm.stack.push(None),
+            Op::Pop => {
+                self.vm.stack.pop();
+            }
+            Op::Statement => self.limits.check()?,
+
+            // ============ variables ============
+            Op::GetLocal(slot, name) => {
+                let value = self.vm.stack[self.frame().base + slot as usize].clone();
+                let value = value.ok_or_else(|| uninitialized(self.token(name)))?;
+                self.vm.push(value);
+            }
+            Op::SetLocal(slot) => {
+                let value = self.vm.peek().clone();
+                let slot = self.frame().base + slot as usize;
+                self.vm.stack[slot] = Some(value);
+            }
+            Op::GetUpvalue(index, name) => {
+                let upvalue = self.frame().closure.upvalues[index as usize].clone();
+                let value = match &*upvalue.borrow() {
+                    Upvalue::Open(slot) => self.vm.stack[*slot].clone(),
+                    Upvalue::Closed(value) => value.clone(),
+                };
+                let value = value.ok_or_else(|| uninitialized(self.token(name)))?;
+                self.vm.push(value);
+            }
+            Op::SetUpvalue(index) => {
+                let value = self.vm.peek().clone();
+                let upvalue = self.frame().closure.upvalues[index as usize].clone();
+                match &mut *upvalue.borrow_mut() {
+                    Upvalue::Open(slot) => self.vm.stack[*slot] = Some(value),
+                    Upvalue::Closed(closed) => *closed = Some(value),
+                }
+            }
+            Op::GetGlobal(name) => {
+                let value = self
+                    .frame()
+                    .closure
+                    .globals
+                    .borrow()
+                    .get(self.token(name))?;
+                self.vm.push(value);
+            }
+            Op::SetGlobal(name) => {
+                let value = self.vm.peek().clone();
+                self.frame()
+                    .closure
+                    .globals
+                    .borrow_mut()
+                    .assign(self.token(name), &value)?;
+            }
+            Op::DefineGlobal(name) => {
+                let value = self.vm.pop();
+                let name = self.token(name).lexeme.clone();
+                self.frame()
+                    .closure
+                    .globals
+                    .borrow_mut()
+                    .define(name, value);
+            }
+            Op::DeclareGlobal(name) => {
+                let name = self.token(name).lexeme.clone();
+                self.frame().closure.globals.borrow_mut().declare(name);
+            }
+
+            // ============ properties and indexing ============
+            Op::GetProperty(name) => {
+                let object = self.vm.pop();
+                let value = get_property(object, self.token(name))?;
+                self.vm.push(value);
+            }
+            Op::RequireInstance(name) => {
+                expect_instance(self.vm.peek().clone(), self.token(name))?;
+            }
+            Op::SetProperty(name) => {
+                let value = self.vm.pop();
+                let object = self.vm.pop();
+                let name = self.token(name);
+                expect_instance(object, name)?
+                    .borrow_mut()
+                    .set(name, value.clone());
+                self.vm.push(value);
+            }
+            Op::GetIndex(bracket) => {
+                let index = self.vm.pop();
+                let object = self.vm.pop();
+                let value = index_get(object, index, self.token(bracket))?;
+                self.vm.push(value);
+            }
+            Op::SetIndex(bracket) => {
+                let value = self.vm.pop();
+                let index = self.vm.pop();
+                let object = self.vm.pop();
+                let value = index_set(object, index, value, self.token(bracket))?;
+                self.vm.push(value);
+            }
+
+            // ============ operators ============
+            Op::Binary(operator) => {
+                let right = self.vm.pop();
+                let left = self.vm.pop();
+                let value = binary_op(left, self.token(operator), right)?;
+                self.vm.push(value);
+            }
+            Op::Negate(operator) => {
+                let right = self.vm.pop();
+                let value = unary_op(self.token(operator), right)?;
+                self.vm.push(value);
+            }
+            Op::Not => {
+                let right = self.vm.pop();
+                self.vm.push(Value::Boolean(!right.is_truthy()));
+            }
+
+            Op::Print => {
+                let value = self.vm.pop();
+                self.write_output(&value.as_string())?;
+            }
+            Op::Echo => {
+                let value = self.vm.pop();
+                self.write_output(&value.to_string())?;
+            }
+
+            // ============ control flow ============
+            Op::Jump(target) => self.jump(target),
+            Op::JumpIfFalse(target) => {
+                if !self.vm.peek().is_truthy() {
+                    self.jump(target);
+                }
+            }
+            Op::Call(count, site) => self.call_value(count as usize, site)?,
+            Op::Closure(index) => {
+                let closure = self.make_closure(index);
+                self.vm.push(Value::Callable(Rc::new(LoxCallable::Compiled {
+                    closure,
+                    receiver: None,
+                })));
+            }
+            Op::CloseUpvalue => {
+                let top = self.vm.stack.len() - 1;
+                self.vm.close_upvalues(top);
+                self.vm.stack.pop();
+            }
+            Op::Return => return Ok(Some(self.return_from_frame())),
+            Op::Class(name, count) => {
+                let methods = self
+                    .vm
+                    .pop_many(count as usize)
+                    .into_iter()
+                    .map(|method| match method {
+                        Value::Callable(callable) => (callable.name(), (*callable).clone()),
+                        _ => unreachable!("methods compile to closures"),
+                    })
+                    .collect();
+                let name = self.token(name).lexeme.clone();
+                self.vm
+                    .push(Value::Class(Rc::new(LoxClass::new(name, methods))));
+            }
+
+            // ============ collections ============
+            Op::List(count) => {
+                let elements = self.vm.pop_many(count as usize);
+                self.vm.push(Value::list(elements));
+            }
+            Op::CheckKey(brace) => {
+                map_key(self.vm.peek(), self.token(brace))?;
+            }
+            Op::Map(count) => {
+                let mut map = LoxMap::default();
+                let entries = self.vm.pop_many(2 * count as usize);
+                for entry in entries.chunks_exact(2) {
+                    let key = HashKey::from_value(&entry[0])
+                        .expect("keys are checked as they are pushed");
+                    map.insert(key, entry[1].clone());
+                }
+                self.vm.push(Value::map(map));
+            }
+
+            // ============ exceptions ============
+            Op::Throw(keyword) => {
+                let value = self.vm.pop();
+                return Err(throw_value(self.token(keyword), value));
+            }
+            Op::PushHandler(target, catch) => self.vm.handlers.push(Handler {
+                frame: self.vm.frames.len() - 1,
+                stack: self.vm.stack.len(),
+                call_stack: self.call_stack.len(),
+                target: target as usize,
+                catch,
+            }),
+            Op::PopHandler => {
+                self.vm.handlers.pop();
+            }
+            Op::Rethrow(slot) => match &self.vm.stack[self.frame().base + slot as usize] {
+                Some(Value::Error(err)) => return Err(RuntimeSignal::Error((**err).clone())),
+                _ => unreachable!("finally handlers store the error they caught"),
+            },
+            Op::EscapedReturn => {
+                return Err(RuntimeSignal::Error(
+                    RuntimeSignal::Return(None).into_error(),
+                ));
+            }
+
+            // ============ modules ============
+            Op::Import(index) => {
+                let import = self.frame().closure.function.chunk.imports[index as usize].clone();
+                let bindings = self.import_bindings(&import)?;
+                let globals = self.frame().closure.globals.clone();
+                for (name, value) in bindings {
+                    globals.borrow_mut().define(name, value);
+                }
+            }
+            Op::Export(name) => {
+                let name = self.token(name).lexeme.clone();
+                self.modules.export(name);
+            }
+        }
+        Ok(None)
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.vm.frames.last_mut().expect("a frame is running").ip = target as usize;
+    }
+
+    fn make_closure(&mut self, index: u32) -> Rc<Closure> {
+        self.maybe_collect_garbage();
+        let frame = self.frame();
+        let function = frame.closure.function.chunk.functions[index as usize].clone();
+        let enclosing = frame.closure.clone();
+        let base = frame.base;
+
+        let upvalues = function
+            .upvalues
+            .iter()
+            .map(|upvalue| {
+                if upvalue.is_local {
+                    self.vm.capture_upvalue(base + upvalue.index as usize, &self.heap)
+                } else {
+                    enclosing.upvalues[upvalue.index as usize].clone()
+                }
+            })
+            .collect();
+
+        Rc::new(Closure {
+            function,
+            upvalues,
+            globals: enclosing.globals.clone(),
+        })
+    }
+
+    // compiled callees get a frame on this stack; anything else is called through the host
+    fn call_value(&mut self, count: usize, site: u32) -> Result<(), RuntimeSignal> {
+        let callee_slot = self.vm.stack.len() - count - 1;
+        let callee = self.vm.stack[callee_slot]
+            .clone()
+            .expect("operands are always initialized");
+
+        let call_site = &self.frame().closure.function.chunk.call_sites[site as usize];
+        let function = check_call(&callee, count, &call_site.paren, &call_site.callee)?;
+        self.check_call_depth(&call_site.paren)?;
+        let line = call_site.paren.line;
+        self.call_stack.push(CallFrame { function, line });
+
+        if let Some((closure, receiver)) = frame_target(&callee) {
+            if let Some(receiver) = receiver {
+                self.vm.stack[callee_slot] = Some(receiver);
+            }
+            self.vm.frames.push(Frame {
+                closure,
+                ip: 0,
+                base: callee_slot,
+                call_site: Some(site),
+            });
+            return Ok(());
+        }
+
+        let args = self.vm.pop_many(count);
+        self.vm.stack.pop();
+        let mut result = match &callee {
+            Value::Class(class) => LoxClass::call(class, self, args),
+            Value::Callable(callable) => callable.call(self, args),
+            _ => unreachable!("non callable values are rejected above"),
+        };
+
+        if let Err(RuntimeSignal::Error(err)) = &mut result
+            && err.trace().is_empty()
+        {
+            err.locate_at(&self.frame().closure.function.chunk.call_sites[site as usize].paren);
+            err.set_trace(self.call_stack.clone());
+        }
+
+        self.call_stack.pop();
+        self.vm.push(result?);
+        Ok(())
+    }
+
+    fn return_from_frame(&mut self) -> Value {
+        let result = self.vm.pop();
+        let frame = self.vm.frames.pop().expect("a frame is running");
+        // initializers always hand back the instance they were bound to
+        let value = if frame.closure.function.is_initializer {
+            self.vm.stack[frame.base]
+                .clone()
+                .expect("'this' is always initialized")
+        } else {
+            result
+        };
+
+        self.vm.close_upvalues(frame.base);
+        self.vm.stack.truncate(frame.base);
+        if frame.call_site.is_some() {
+            self.call_stack.pop();
+        }
+        value
+    }
+
+    // resumes at the innermost handler that takes the error, or unwinds to 'entry'
+    fn unwind(
+        &mut self,
+        signal: RuntimeSignal,
+        entry: usize,
+        call_depth: usize,
+    ) -> Result<(), RuntimeSignal> {
+        let mut err = signal.into_error();
+
+        while self
+            .vm
+            .handlers
+            .last()
+            .is_some_and(|handler| handler.frame >= entry)
+        {
+            let handler = self.vm.handlers.pop().expect("checked above");
+            // catch clauses only see runtime errors; finally blocks see everything
+            if handler.catch && err.kind() != ErrorKind::Runtime {
+                continue;
+            }
+
+            if handler.frame + 1 < self.vm.frames.len() {
+                self.record_trace(&mut err);
+            }
+            self.vm.close_upvalues(handler.stack);
+            self.vm.stack.truncate(handler.stack);
+            self.vm.frames.truncate(handler.frame + 1);
+            self.call_stack.truncate(handler.call_stack);
+            self.jump(handler.target as u32);
+
+            let value = if handler.catch {
+                caught_value(&err)
+            } else {
+                Value::Error(Rc::new(err))
+            };
+            self.vm.push(value);
+            return Ok(());
+        }
+
+        if self.vm.frames.len() > entry + 1 {
+            self.record_trace(&mut err);
+        }
+        let base = self.vm.frames[entry].base;
+        self.vm.close_upvalues(base);
+        self.vm.stack.truncate(base);
+        self.vm.frames.truncate(entry);
+        self.call_stack.truncate(call_depth);
+        Err(RuntimeSignal::Error(err))
+    }
+
+    // the innermost call an error escapes locates and traces it, as in the tree-walker
+    fn record_trace(&self, err: &mut LoxError) {
+        let frames = &self.vm.frames;
+        let (callee, caller) = (&frames[frames.len() - 1], &frames[frames.len() - 2]);
+        if let Some(site) = callee.call_site
+            && err.trace().is_empty()
+        {
+            err.locate_at(&caller.closure.function.chunk.call_sites[site as usize].paren);
+            err.set_trace(self.call_stack.clone());
+        }
+    }
+}
+
+// the closure and receiver of a callee that runs in a frame of its own
+fn frame_target(callee: &Value) -> Option<(Rc<Closure>, Option<Value>)> {
+    let callable = match callee {
+        Value::Callable(callable) => (**callable).clone(),
+        Value::Class(class) => class.bind("init", LoxInstance::instantiate(class.clone()))?,
+        _ => return None,
+    };
+    match callable {
+        LoxCallable::Compiled { closure, receiver } => Some((closure, receiver)),
+        _ => None,
+    }
+}
@@ -1,5 +1,14 @@
 pub mod ast;
+pub mod compiler;
+pub mod debug;
 pub mod error;
+pub mod formatter;
 pub mod interpreter;
+pub mod lint;
+pub mod lox;
+pub mod lsp;
+pub mod repl;
 pub mod resolver;
 pub mod scanner;
+
+pub use lox::Lox;
@@ -0,0 +1,344 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    ast::expression::{Expr, LiteralValue},
+    error::LoxError,
+    interpreter::stmt::Stmt,
+    lox,
+    resolver::{throwaway_interpreter, DeclarationKind, Resolver, Symbols},
+    scanner::{token::Token, token_type::TokenType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,     // a local variable or parameter that is never read
+    Shadowing,          // a local declaration hiding one from an enclosing scope
+    UnreachableCode,    // statements after a 'return', 'break', 'continue' or 'throw'
+    FunctionAssignment, // assigning to the name of a declared function
+    LiteralComparison,  // comparing a value with a literal of another type
+    ConstantCondition,  // an 'if' or loop condition that can only go one way
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnusedVariable,
+        Lint::Shadowing,
+        Lint::UnreachableCode,
+        Lint::FunctionAssignment,
+        Lint::LiteralComparison,
+        Lint::ConstantCondition,
+    ];
+
+    // how the lint is named on the command line and in warnings
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::Shadowing => "shadowing",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::FunctionAssignment => "function-assignment",
+            Lint::LiteralComparison => "literal-comparison",
+            Lint::ConstantCondition => "constant-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+// which lints are reported; every one of them unless it has been allowed
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    allowed: HashSet<Lint>,
+}
+
+impl LintConfig {
+    pub fn allow(&mut self, lint: Lint) {
+        self.allowed.insert(lint);
+    }
+
+    pub fn warn(&mut self, lint: Lint) {
+        self.allowed.remove(&lint);
+    }
+
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        !self.allowed.contains(&lint)
+    }
+}
+
+// a suspicious but legal piece of code; unlike errors, warnings never stop a script running
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub lint: Lint,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Warning on [line {}]: {} [{}]",
+            self.line,
+            self.message,
+            self.lint.name()
+        )
+    }
+}
+
+// the warnings for a whole file, or its errors if it doesn't compile
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Warning>, Vec<LoxError>> {
+    let statements = lox::compile(&mut throwaway_interpreter(), source)?;
+    check(&statements, config)
+}
+
+// the warnings for statements that have already been parsed
+pub fn check(statements: &[Stmt], config: &LintConfig) -> Result<Vec<Warning>, Vec<LoxError>> {
+    let mut interpreter = throwaway_interpreter();
+    let mut resolver = Resolver::with_lints(&mut interpreter, config.clone());
+    resolver.resolve(statements).map_err(lox::into_errors)?;
+    Ok(resolver.into_warnings())
+}
+
+// collects warnings while the resolver walks a program, see 'Resolver::with_lints'
+pub(crate) struct Lints {
+    config: LintConfig,
+    warnings: Vec<Warning>,
+    read: HashSet<usize>, // declarations whose value is used somewhere
+}
+
+impl Lints {
+    pub(crate) fn new(config: LintConfig) -> Self {
+        Lints {
+            config,
+            warnings: Vec::new(),
+            read: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn warn(&mut self, lint: Lint, token: &Token, message: String) {
+        if !self.config.is_enabled(lint) {
+            return;
+        }
+        // a 'return' ending nested blocks is reported once, however many of them it ends
+        let repeated = self.warnings.iter().any(|warning| {
+            warning.lint == lint && warning.line == token.line && warning.column == token.column
+        });
+        if !repeated {
+            self.warnings.push(Warning {
+                lint,
+                line: token.line,
+                column: token.column,
+                length: token.length,
+                message,
+            });
+        }
+    }
+
+    pub(crate) fn read(&mut self, declaration: usize) {
+        self.read.insert(declaration);
+    }
+
+    pub(crate) fn check_unreachable(&mut self, stmts: &[Stmt]) {
+        let Some(position) = stmts.iter().position(|stmt| exit_keyword(stmt).is_some()) else {
+            return;
+        };
+        if position + 1 < stmts.len()
+            && let Some(keyword) = exit_keyword(&stmts[position])
+        {
+            self.warn(
+                Lint::UnreachableCode,
+                keyword,
+                format!("code after this '{}' is unreachable", keyword.lexeme),
+            );
+        }
+    }
+
+    pub(crate) fn check_condition(&mut self, keyword: &Token, condition: &Expr, looping: bool) {
+        // 'while (true)' and 'for (;;)' loop until something breaks out of them
+        let forever = matches!(
+            condition,
+            Expr::Literal {
+                value: LiteralValue::Boolean(true),
+                ..
+            }
+        );
+        if looping && forever {
+            return;
+        }
+        if let Some(truthy) = truthiness(condition) {
+            self.warn(
+                Lint::ConstantCondition,
+                keyword,
+                format!("this condition is always {truthy}"),
+            );
+        }
+    }
+
+    pub(crate) fn check_comparison(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+        let consequence = match operator.token_type {
+            TokenType::EqualEqual => "always false",
+            TokenType::BangEqual => "always true",
+            TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual => "a runtime error",
+            _ => return,
+        };
+        let literal = |expr: &Expr| matches!(expr, Expr::Literal { .. });
+        if !literal(left) && !literal(right) {
+            return;
+        }
+        if let (Some(left_type), Some(right_type)) = (static_type(left), static_type(right))
+            && left_type != right_type
+        {
+            self.warn(
+                Lint::LiteralComparison,
+                operator,
+                format!(
+                    "comparing {} with {} is {consequence}",
+                    left_type.describe(),
+                    right_type.describe()
+                ),
+            );
+        }
+    }
+
+    // adds the warnings that need the whole program to have been seen
+    pub(crate) fn finish(mut self, symbols: &Symbols) -> Vec<Warning> {
+        for (index, declaration) in symbols.declarations.iter().enumerate() {
+            // globals may be used by importers, or by code typed into a REPL later
+            if declaration.global || self.read.contains(&index) {
+                continue;
+            }
+            let what = match declaration.kind {
+                DeclarationKind::Variable => "variable",
+                DeclarationKind::Parameter => "parameter",
+                _ => continue,
+            };
+            self.warn(
+                Lint::UnusedVariable,
+                &declaration.name,
+                format!("unused {what} '{}'", declaration.name.lexeme),
+            );
+        }
+        self.warnings
+            .sort_by_key(|warning| (warning.line, warning.column));
+        self.warnings
+    }
+}
+
+// the keyword that makes 'stmt' leave its block every time it runs
+fn exit_keyword(stmt: &Stmt) -> Option<&Token> {
+    match stmt {
+        Stmt::Return(keyword, _)
+        | Stmt::Throw(keyword, _)
+        | Stmt::Break(keyword, _)
+        | Stmt::Continue(keyword, _) => Some(keyword),
+        Stmt::Block(stmts) => stmts.iter().find_map(exit_keyword),
+        Stmt::If(if_conditions) => {
+            let then_exit = exit_keyword(&if_conditions.then_branch)?;
+            if_conditions
+                .else_branch
+                .as_ref()
+                .and_then(|else_branch| exit_keyword(else_branch))
+                .map(|_| then_exit)
+        }
+        _ => None,
+    }
+}
+
+// the kinds of value that an expression is known to produce without running it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Number,
+    String,
+    Boolean,
+    Nil,
+    List,
+    Map,
+    Function,
+}
+
+impl Type {
+    fn describe(self) -> &'static str {
+        match self {
+            Type::Number => "a number",
+            Type::String => "a string",
+            Type::Boolean => "a boolean",
+            Type::Nil => "nil",
+            Type::List => "a list",
+            Type::Map => "a map",
+            Type::Function => "a function",
+        }
+    }
+}
+
+fn static_type(expr: &Expr) -> Option<Type> {
+    match expr {
+        Expr::Literal { value, .. } => Some(match value {
+            LiteralValue::Number(_) => Type::Number,
+            LiteralValue::String(_) => Type::String,
+            LiteralValue::Boolean(_) => Type::Boolean,
+            LiteralValue::Nil => Type::Nil,
+        }),
+        Expr::Grouping { expression, .. } => static_type(expression),
+        Expr::Unary { token, .. } => match token.token_type {
+            TokenType::Minus => Some(Type::Number),
+            TokenType::Bang => Some(Type::Boolean),
+            _ => None,
+        },
+        Expr::Binary {
+            left_expr,
+            operator,
+            right_expr,
+            ..
+        } => match operator.token_type {
+            TokenType::Minus | TokenType::Star | TokenType::Slash => Some(Type::Number),
+            // '+' adds two numbers or joins two strings, and fails on anything else
+            TokenType::Plus => static_type(left_expr).filter(|left| {
+                matches!(left, Type::Number | Type::String)
+                    && static_type(right_expr) == Some(*left)
+            }),
+            _ => Some(Type::Boolean),
+        },
+        Expr::List { .. } => Some(Type::List),
+        Expr::Map { .. } => Some(Type::Map),
+        Expr::Lambda { .. } => Some(Type::Function),
+        _ => None,
+    }
+}
+
+// whether a condition is known to pass or fail before the program runs
+fn truthiness(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Literal { value, .. } => Some(match value {
+            LiteralValue::Nil => false,
+            LiteralValue::Boolean(value) => *value,
+            _ => true,
+        }),
+        Expr::Grouping { expression, .. } => truthiness(expression),
+        Expr::Unary {
+            token, expression, ..
+        } if token.token_type == TokenType::Bang => truthiness(expression).map(|truthy| !truthy),
+        Expr::Logical {
+            left,
+            operator,
+            right,
+            ..
+        } => {
+            let short_circuits = operator.token_type == TokenType::Or;
+            match truthiness(left) {
+                Some(truthy) if truthy == short_circuits => Some(truthy),
+                Some(_) => truthiness(right),
+                None => None,
+            }
+        }
+        // only nil and false are falsey, so numbers, strings and the rest always pass
+        _ => static_type(expr)
+            .filter(|kind| !matches!(kind, Type::Boolean | Type::Nil))
+            .map(|_| true),
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    ast::parser::Parser,
+    error::{LoxError, RuntimeSignal},
+    interpreter::{stmt::Stmt, values::Value, Interpreter},
+    resolver::Resolver,
+    scanner::Scanner,
+};
+
+// the whole scan -> parse -> resolve -> interpret pipeline behind one handle;
+// state (globals, natives, expr ids) carries over between calls
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self::with_interpreter(Interpreter::new())
+    }
+
+    // e.g. an interpreter built with Interpreter::with_output
+    pub fn with_interpreter(interpreter: Interpreter) -> Self {
+        Lox { interpreter }
+    }
+
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    // scans, parses and resolves; every static error found is returned
+    pub fn compile(&mut self, source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        compile(&mut self.interpreter, source)
+    }
+
+    // runs the source, returning the value of a trailing expression statement (nil otherwise)
+    pub fn eval(&mut self, source: &str) -> Result<Value, Vec<LoxError>> {
+        let statements = self.compile(source)?;
+        self.interpreter.run(&statements).map_err(|err| vec![err])
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// also used by the interpreter to load imported modules
+pub(crate) fn compile(
+    interpreter: &mut Interpreter,
+    source: &str,
+) -> Result<Vec<Stmt>, Vec<LoxError>> {
+    let mut scanner = Scanner::new(source.to_string());
+    let (tokens, scan_errors) = scanner.scan_tokens();
+    if !scan_errors.is_empty() {
+        return Err(into_errors(scan_errors));
+    }
+
+    let mut parser = Parser::with_expr_id(tokens, interpreter.next_expr_id());
+    let (statements, parse_errors) = parser.parse();
+    interpreter.reserve_expr_ids(parser.next_expr_id());
+    if !parse_errors.is_empty() {
+        return Err(into_errors(parse_errors));
+    }
+
+    let mut resolver = Resolver::new(interpreter);
+    resolver.resolve(&statements).map_err(into_errors)?;
+
+    Ok(statements)
+}
+
+pub(crate) fn into_errors(signals: Vec<RuntimeSignal>) -> Vec<LoxError> {
+    signals.into_iter().map(RuntimeSignal::into_error).collect()
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    lsp::{document::Document, json::Json},
+    scanner::token::Token,
+};
+
+mod document;
+pub mod json;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+// LSP enum values
+const TEXT_SYNC_FULL: usize = 1;
+const SEVERITY_ERROR: usize = 1;
+const SYMBOL_FUNCTION: usize = 12;
+
+// a language server for the documents an editor has open; messages go in and the replies
+// and notifications they cause come out, so it can be driven without any transport
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutting_down: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Some once the client sent 'exit': 0 if it asked to shut down first, 1 otherwise
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    // the raw text of one message; malformed JSON gets an error response
+    pub fn handle_text(&mut self, text: &str) -> Vec<Json> {
+        match Json::parse(text) {
+            Ok(message) => self.handle(&message),
+            Err(err) => vec![error_response(Json::Null, PARSE_ERROR, err)],
+        }
+    }
+
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let id = message.get("id");
+        let params = message.get("params");
+        let Some(method) = message.get("method").as_str() else {
+            // a response to something we never send, or garbage
+            if id.is_null() {
+                return Vec::new();
+            }
+            return vec![error_response(
+                id.clone(),
+                INVALID_REQUEST,
+                "missing method".into(),
+            )];
+        };
+
+        // requests carry an id and always get a response; notifications never do
+        if id.is_null() {
+            return self.notification(method, params);
+        }
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutting_down = true;
+                Some(Json::Null)
+            }
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/references" => Some(self.references(params)),
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params)),
+            _ => None,
+        };
+
+        vec![match result {
+            Some(result) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("result", result),
+            ]),
+            None => error_response(
+                id.clone(),
+                METHOD_NOT_FOUND,
+                format!("unsupported method '{method}'"),
+            ),
+        }]
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let document = params.get("textDocument");
+        let Some(uri) = document.get("uri").as_str() else {
+            if method == "exit" {
+                self.exit_code = Some(if self.shutting_down { 0 } else { 1 });
+            }
+            return Vec::new();
+        };
+
+        let text = match method {
+            "textDocument/didOpen" => document.get("text").as_str(),
+            // full sync: each change carries the whole new text
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text").as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+
+        let document = Document::analyze(text.to_string());
+        let diagnostics = document
+            .diagnostics()
+            .iter()
+            .map(|err| {
+                Json::object([
+                    ("range", document.error_range(err)),
+                    ("severity", SEVERITY_ERROR.into()),
+                    ("source", "rlox".into()),
+                    ("message", err.message().into()),
+                ])
+            })
+            .collect();
+        self.documents.insert(uri.to_string(), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    // the document a request is about and the declaration under its cursor
+    fn target<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params.get("textDocument").get("uri").as_str()?;
+        let document = self.documents.get(uri)?;
+        let declaration = document.declaration_at(params.get("position"))?;
+        Some((uri, document, declaration))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        match self.target(params) {
+            Some((uri, document, declaration)) => {
+                location(uri, document, &document.declaration(declaration).name)
+            }
+            None => Json::Null,
+        }
+    }
+
+    fn references(&self, params: &Json) -> Json {
+        let Some((uri, document, declaration)) = self.target(params) else {
+            return Json::Array(Vec::new());
+        };
+        let include_declaration = params
+            .get("context")
+            .get("includeDeclaration")
+            .as_bool()
+            .unwrap_or(true);
+
+        Json::Array(
+            document
+                .references(declaration, include_declaration)
+                .into_iter()
+                .map(|token| location(uri, document, token))
+                .collect(),
+        )
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((_, document, declaration)) = self.target(params) else {
+            return Json::Null;
+        };
+        Json::object([(
+            "contents",
+            Json::object([
+                ("kind", "markdown".into()),
+                ("value", document.hover(declaration).into()),
+            ]),
+        )])
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let document = params
+            .get("textDocument")
+            .get("uri")
+            .as_str()
+            .and_then(|uri| self.documents.get(uri));
+        let Some(document) = document else {
+            return Json::Array(Vec::new());
+        };
+
+        Json::Array(
+            document
+                .functions()
+                .iter()
+                .map(|function| {
+                    Json::object([
+                        ("name", function.name.lexeme.as_str().into()),
+                        ("detail", format!("({})", function.params.join(", ")).into()),
+                        ("kind", SYMBOL_FUNCTION.into()),
+                        ("range", document.span_range(&function.start, &function.end)),
+                        ("selectionRange", document.token_range(&function.name)),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", TEXT_SYNC_FULL.into()),
+                ("definitionProvider", true.into()),
+                ("referencesProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        ("serverInfo", Json::object([("name", "rlox-lsp".into())])),
+    ])
+}
+
+fn location(uri: &str, document: &Document, token: &Token) -> Json {
+    Json::object([("uri", uri.into()), ("range", document.token_range(token))])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, code: i32, message: String) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code.into())),
+                ("message", message.into()),
+            ]),
+        ),
+    ])
+}
+
+// runs the server over a Content-Length framed stream until 'exit' or end of input,
+// returning the process exit code
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        for reply in server.handle_text(&body) {
+            write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
+
+// None at end of input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    // without a length there is no telling where the body ends, so the stream can't be read on
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message header has no valid Content-Length",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
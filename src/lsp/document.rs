@@ -0,0 +1,237 @@
+use crate::{
+    ast::parser::Parser,
+    error::{LoxError, RuntimeSignal},
+    interpreter::stmt::Stmt,
+    lsp::json::Json,
+    resolver::{throwaway_interpreter, Declaration, DeclarationKind, Resolver, Symbols},
+    scanner::{token::Token, token_type::TokenType, Scanner},
+};
+
+// a top-level 'fun', with the tokens its outline entry spans
+pub struct FunctionSymbol {
+    pub name: Token,
+    pub params: Vec<String>,
+    pub start: Token, // 'export' or 'fun'
+    pub end: Token,   // the closing brace of the body
+}
+
+// an open file, analysed whenever its text changes
+pub struct Document {
+    text: String,
+    diagnostics: Vec<LoxError>,
+    symbols: Symbols,
+    functions: Vec<FunctionSymbol>,
+}
+
+impl Document {
+    pub fn analyze(text: String) -> Self {
+        let mut scanner = Scanner::new(text.clone());
+        let (tokens, scan_errors) = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens.clone());
+        let (statements, parse_errors) = parser.parse();
+
+        let mut interpreter = throwaway_interpreter();
+        let mut resolver = Resolver::with_symbols(&mut interpreter);
+        let resolve_errors = resolver.resolve(&statements).err().unwrap_or_default();
+        let symbols = resolver.into_symbols();
+
+        // tokens missing after a scan error only cause more confusing parse errors, but
+        // whatever did parse is still worth resolving for navigation
+        let diagnostics = if scan_errors.is_empty() {
+            parse_errors.into_iter().chain(resolve_errors).collect()
+        } else {
+            scan_errors
+        };
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(RuntimeSignal::into_error)
+            .collect();
+
+        Document {
+            functions: top_level_functions(&statements, &tokens),
+            text,
+            diagnostics,
+            symbols,
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[LoxError] {
+        &self.diagnostics
+    }
+
+    pub fn functions(&self) -> &[FunctionSymbol] {
+        &self.functions
+    }
+
+    pub fn declaration(&self, index: usize) -> &Declaration {
+        &self.symbols.declarations[index]
+    }
+
+    // the declaration of the name under the cursor, whether that is a use or the declaration
+    pub fn declaration_at(&self, position: &Json) -> Option<usize> {
+        let (line, column) = self.byte_position(position)?;
+        let covers = |token: &Token| {
+            token.line == line && token.column <= column && column < token.column + token.length
+        };
+
+        self.symbols
+            .references
+            .iter()
+            .find(|reference| covers(&reference.name))
+            .and_then(|reference| reference.declaration)
+            .or_else(|| {
+                self.symbols
+                    .declarations
+                    .iter()
+                    .position(|declaration| covers(&declaration.name))
+            })
+    }
+
+    // in source order
+    pub fn references(&self, declaration: usize, include_declaration: bool) -> Vec<&Token> {
+        let mut tokens: Vec<&Token> = self
+            .symbols
+            .references
+            .iter()
+            .filter(|reference| reference.declaration == Some(declaration))
+            .map(|reference| &reference.name)
+            .collect();
+        if include_declaration {
+            tokens.push(&self.declaration(declaration).name);
+        }
+        tokens.sort_by_key(|token| token.offset);
+        tokens.dedup_by_key(|token| token.offset);
+        tokens
+    }
+
+    pub fn hover(&self, declaration: usize) -> String {
+        let declaration = self.declaration(declaration);
+        let kind = match declaration.kind {
+            DeclarationKind::Variable if declaration.global => "global variable",
+            DeclarationKind::Variable => "local variable",
+            DeclarationKind::Function => "function",
+            DeclarationKind::Class => "class",
+            DeclarationKind::Parameter => "parameter",
+            DeclarationKind::CatchVariable => "caught error",
+            DeclarationKind::Import => "imported name",
+        };
+        let line = declaration.name.line;
+        let source = self.line_text(line).map_or("", str::trim);
+        format!(
+            "```lox\n{source}\n```\n{kind} `{}`, declared on line {line}",
+            declaration.name.lexeme
+        )
+    }
+
+    pub fn token_range(&self, token: &Token) -> Json {
+        self.range(token.line, token.column, token.length)
+    }
+
+    pub fn span_range(&self, start: &Token, end: &Token) -> Json {
+        Json::object([
+            ("start", self.position(start.line, start.column)),
+            ("end", self.position(end.line, end.column + end.length)),
+        ])
+    }
+
+    pub fn error_range(&self, err: &LoxError) -> Json {
+        match err.column() {
+            Some(column) => self.range(err.line(), column, err.length()),
+            // errors without a column cover their whole line
+            None => {
+                let length = self.line_text(err.line()).map_or(0, str::len);
+                self.range(err.line(), 1, length)
+            }
+        }
+    }
+
+    // 'line' and 'column' are 1-based like tokens; the span is cut off at the end of the line
+    fn range(&self, line: usize, column: usize, length: usize) -> Json {
+        let line_length = self.line_text(line).map_or(0, str::len);
+        let end = (column + length).min(line_length + 1).max(column);
+        Json::object([
+            ("start", self.position(line, column)),
+            ("end", self.position(line, end)),
+        ])
+    }
+
+    // LSP positions are 0-based, with columns counted in UTF-16 code units
+    fn position(&self, line: usize, column: usize) -> Json {
+        let text = self.line_text(line).unwrap_or("");
+        let prefix = text.get(..column.saturating_sub(1)).unwrap_or(text);
+        Json::object([
+            ("line", line.saturating_sub(1).into()),
+            ("character", prefix.encode_utf16().count().into()),
+        ])
+    }
+
+    // the inverse of 'position'
+    fn byte_position(&self, position: &Json) -> Option<(usize, usize)> {
+        let line = position.get("line").as_usize()? + 1;
+        let character = position.get("character").as_usize()?;
+        let text = self.line_text(line)?;
+
+        let mut units = 0;
+        for (byte, c) in text.char_indices() {
+            if units >= character {
+                return Some((line, byte + 1));
+            }
+            units += c.len_utf16();
+        }
+        Some((line, text.len() + 1))
+    }
+
+    fn line_text(&self, line: usize) -> Option<&str> {
+        self.text.lines().nth(line.checked_sub(1)?)
+    }
+}
+
+fn top_level_functions(statements: &[Stmt], tokens: &[Token]) -> Vec<FunctionSymbol> {
+    statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Function(fun_def) => Some(fun_def),
+            Stmt::Export(declaration) => match &**declaration {
+                Stmt::Function(fun_def) => Some(fun_def),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter_map(|fun_def| {
+            let name = tokens
+                .iter()
+                .position(|token| token.offset == fun_def.name.offset)?;
+            let (start, end) = function_span(tokens, name)?;
+            Some(FunctionSymbol {
+                name: fun_def.name.clone(),
+                params: fun_def.params.iter().map(|p| p.lexeme.clone()).collect(),
+                start: start.clone(),
+                end: end.clone(),
+            })
+        })
+        .collect()
+}
+
+// from the keywords before a function's name to the brace that closes its body
+fn function_span(tokens: &[Token], name: usize) -> Option<(&Token, &Token)> {
+    let mut start = name;
+    while start > 0
+        && matches!(
+            tokens[start - 1].token_type,
+            TokenType::Fun | TokenType::Export
+        )
+    {
+        start -= 1;
+    }
+
+    let mut depth = 0;
+    for token in &tokens[name..] {
+        match token.token_type {
+            TokenType::LeftBrace => depth += 1,
+            TokenType::RightBrace if depth == 1 => return Some((&tokens[start], token)),
+            TokenType::RightBrace => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
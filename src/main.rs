@@ -1,75 +1,247 @@
 use std::{
     env::{self},
-    fs, io,
+    fs,
+    io::{self, Read},
+    process,
 };
 
-use rlox::{ast::parser::Parser, interpreter::Interpreter, scanner::Scanner};
+use rlox::{
+    debug::{self, Console},
+    error::SourceFile,
+    formatter,
+    interpreter::{self, debugger::Debugger, Backend},
+    lint::{self, Lint, LintConfig},
+    repl, Lox,
+};
+
+// deep lox recursion needs a deep native stack on the tree-walker; the memory is only
+// committed as the stack actually grows
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let length = args.len();
-    if length > 2 {
-        print!("USAGE: ./binary /path/to/file \n OR \n cargo run -- path/to/file")
-    } else if length == 2 {
-        run_from_file(args.get(1).unwrap()).unwrap_or_else(|err| {
-            panic!("failed to run from file: {:?}", err);
-        });
-    } else {
-        run_by_prompt().unwrap_or_else(|err| {
+    let interpreter = interpreter::spawn_with_stack(INTERPRETER_STACK_SIZE, start)
+        .expect("failed to spawn interpreter thread");
+
+    // the panic message was already printed by the thread
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn start() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // '--vm' runs on the bytecode backend instead of the tree-walker
+    let backend = match args.iter().position(|arg| arg == "--vm") {
+        Some(index) => {
+            args.remove(index);
+            Backend::Bytecode
+        }
+        None => Backend::TreeWalk,
+    };
+
+    // '--allow NAME' turns off one of the lints reported before a script runs, or by 'lint'
+    let mut lints = LintConfig::default();
+    while let Some(index) = args.iter().position(|arg| arg == "--allow") {
+        let name = args.get(index + 1).cloned().unwrap_or_default();
+        let Some(lint) = Lint::from_name(&name) else {
+            let names: Vec<&str> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+            eprintln!(
+                "unknown lint '{name}', expected one of: {}",
+                names.join(", ")
+            );
+            process::exit(1);
+        };
+        lints.allow(lint);
+        args.drain(index..=index + 1);
+    }
+
+    match args.as_slice() {
+        [command, rest @ ..] if command == "fmt" => process::exit(format_files(rest)),
+        [command, rest @ ..] if command == "lint" => process::exit(lint_files(rest, &lints)),
+        [command, flag] if command == "debug" && flag == "--dap" => {
+            debug::dap::serve(io::stdin().lock(), io::stdout()).unwrap_or_else(|err| {
+                panic!("debug adapter failed: {:?}", err);
+            })
+        }
+        [command, path] if command == "debug" => debug_file(path, &lints).unwrap_or_else(|err| {
+            panic!("failed to debug file: {:?}", err);
+        }),
+        [] => repl::run_prompt(backend).unwrap_or_else(|err| {
             panic!("failed to run interactive prompt: {:?}", err);
-        });
+        }),
+        [path] => run_from_file(path, backend, &lints).unwrap_or_else(|err| {
+            panic!("failed to run from file: {:?}", err);
+        }),
+        _ => print!(
+            "USAGE: ./binary [--vm] /path/to/file \n OR \n cargo run -- [--vm] path/to/file \n OR \n ./binary fmt [--check] [paths...] \n OR \n ./binary debug (/path/to/file | --dap) \n OR \n ./binary lint [paths...] \n lints are reported before running a file and can be turned off with [--allow lint-name]"
+        ),
     }
 }
 
-fn run_from_file(path: &str) -> io::Result<()> {
+fn run_from_file(path: &str, backend: Backend, lints: &LintConfig) -> io::Result<()> {
     let content = fs::read_to_string(path)?;
-    run(content);
+    run(content, path, backend, None, lints);
     Ok(())
 }
 
-fn run_by_prompt() -> io::Result<()> {
-    loop {
-        print!("> ");
+// starts paused on the first statement, taking debugger commands from stdin
+fn debug_file(path: &str, lints: &LintConfig) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let console = Console::new(&content, io::stdin().lock(), io::stdout());
+    run(
+        content,
+        path,
+        Backend::TreeWalk,
+        Some(Debugger::new(console)),
+        lints,
+    );
+    Ok(())
+}
 
-        let mut line = String::new();
-        let bytes_read = io::stdin().read_line(&mut line)?;
+fn run(
+    source: String,
+    file_name: &str,
+    backend: Backend,
+    debugger: Option<Debugger>,
+    lints: &LintConfig,
+) {
+    let source_file = SourceFile::new(file_name, source.as_str());
 
-        if bytes_read == 0 {
-            print!("");
-            break;
+    // scan, parse and resolve
+    let mut lox = Lox::new();
+    let statements = match lox.compile(&source) {
+        Ok(statements) => statements,
+        Err(errors) => {
+            for error in errors {
+                println!("{}", source_file.render(&error));
+            }
+            return;
         }
+    };
 
-        run(line);
+    // warnings go to stderr, so they never mix with what the script prints
+    if let Ok(warnings) = lint::check(&statements, lints) {
+        for warning in warnings {
+            eprintln!("{}", source_file.render_warning(&warning));
+        }
     }
 
-    Ok(())
+    // interpret the AST
+    let interpreter = lox.interpreter();
+    interpreter.set_backend(backend);
+    interpreter.set_source(source_file);
+    interpreter.set_script_path(file_name);
+    if let Some(debugger) = debugger {
+        interpreter.set_debugger(debugger);
+    }
+    interpreter.interpret(&statements);
 }
 
-fn run(source: String) {
-    // scan tokens
-    let mut scanner = Scanner::new(source);
-    let (tokens, errors) = scanner.scan_tokens();
-    if !errors.is_empty() {
-        for error in errors {
-            println!("{error}")
+// 'fmt [--check] [paths...]' rewrites each file in place, or with '--check' only lists the
+// ones that are not formatted; without paths it formats stdin to stdout. The exit code is 1
+// if anything failed or, when checking, needs formatting
+fn format_files(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if paths.is_empty() {
+        let mut source = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut source) {
+            eprintln!("failed to read stdin: {err}");
+            return 1;
         }
-        return;
+        return match format_or_report("<stdin>", &source) {
+            Some(formatted) if check => i32::from(formatted != source),
+            Some(formatted) => {
+                print!("{formatted}");
+                0
+            }
+            None => 1,
+        };
     }
 
-    // parse tokens into AST
-    let mut parser = Parser::new(tokens);
-    let (statements, parse_errors) = parser.parse();
-    if !parse_errors.is_empty() {
-        for error in parse_errors {
-            println!("{error}");
+    let mut status = 0;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("failed to read {path}: {err}");
+                status = 1;
+                continue;
+            }
+        };
+        match format_or_report(path, &source) {
+            Some(formatted) if formatted == source => {}
+            Some(_) if check => {
+                println!("{path}");
+                status = 1;
+            }
+            Some(formatted) => {
+                if let Err(err) = fs::write(path, formatted) {
+                    eprintln!("failed to write {path}: {err}");
+                    status = 1;
+                }
+            }
+            None => status = 1,
         }
-        return;
     }
+    status
+}
 
-    // println!("{:#?}", statements);
+// stdout may be carrying formatted source, so errors go to stderr
+fn format_or_report(file_name: &str, source: &str) -> Option<String> {
+    formatter::format_source(source)
+        .map_err(|errors| {
+            let source_file = SourceFile::new(file_name, source);
+            for error in errors {
+                eprintln!("{}", source_file.render(&error));
+            }
+        })
+        .ok()
+}
 
-    // interpret the AST
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(&statements);
-    // 1. synchronizing in the parser to return a list of errors
+// 'lint [paths...]' reports the warnings for each file, or for stdin without paths. The exit
+// code is 1 if anything failed to compile or there was anything to warn about
+fn lint_files(paths: &[String], config: &LintConfig) -> i32 {
+    let sources: Vec<(String, io::Result<String>)> = if paths.is_empty() {
+        let mut source = String::new();
+        let read = io::stdin().read_to_string(&mut source).map(|_| source);
+        vec![("<stdin>".to_string(), read)]
+    } else {
+        paths
+            .iter()
+            .map(|path| (path.clone(), fs::read_to_string(path)))
+            .collect()
+    };
+
+    let mut status = 0;
+    for (file_name, source) in sources {
+        let source = match source {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("failed to read {file_name}: {err}");
+                status = 1;
+                continue;
+            }
+        };
+        let source_file = SourceFile::new(file_name, source.as_str());
+        match lint::lint_source(&source, config) {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    println!("{}", source_file.render_warning(warning));
+                }
+                if !warnings.is_empty() {
+                    status = 1;
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    println!("{}", source_file.render(&error));
+                }
+                status = 1;
+            }
+        }
+    }
+    status
 }
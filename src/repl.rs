@@ -0,0 +1,161 @@
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    error::SourceFile,
+    interpreter::Backend,
+    scanner::{token_type::TokenType, Scanner},
+    Lox,
+};
+
+// one interactive session: globals, resolved locals and expr ids all survive between inputs
+pub struct Session {
+    lox: Lox,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::with_backend(Backend::default())
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        let mut lox = Lox::new();
+        lox.interpreter().set_backend(backend);
+        Session { lox }
+    }
+
+    // stops the input that is running; see Interpreter::interrupt_handle
+    pub fn interrupt_handle(&mut self) -> Arc<AtomicBool> {
+        self.lox.interpreter().interrupt_handle()
+    }
+
+    pub fn run(&mut self, source: String) {
+        let source_file = SourceFile::new("<repl>", source.as_str());
+
+        let statements = match self.lox.compile(&source) {
+            Ok(statements) => statements,
+            Err(errors) => {
+                for error in errors {
+                    println!("{}", source_file.render(&error));
+                }
+                return;
+            }
+        };
+
+        let interpreter = self.lox.interpreter();
+        interpreter.set_source(source_file);
+        interpreter.interpret_and_echo(&statements);
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// true while the input has more '(', '[' or '{' than closing ones, i.e. the user is mid statement
+pub fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source.to_string());
+    let (tokens, _) = scanner.scan_tokens();
+
+    let mut depth: i64 = 0;
+    for token in tokens {
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+pub fn run_prompt(backend: Backend) -> io::Result<()> {
+    let mut session = Session::with_backend(backend);
+    let interrupt = session.interrupt_handle();
+    let mut buffer = String::new();
+
+    loop {
+        print!("{}", if buffer.is_empty() { "> " } else { ". " });
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        let bytes_read = io::stdin().read_line(&mut line)?;
+
+        if bytes_read == 0 {
+            println!();
+            break;
+        }
+
+        buffer.push_str(&line);
+        if is_incomplete(&buffer) {
+            continue;
+        }
+
+        // Ctrl-C at the prompt still exits; while an input runs it only stops that input
+        interrupt.store(false, Ordering::Relaxed);
+        let _ctrl_c = ctrl_c::interrupt(interrupt.clone());
+        session.run(std::mem::take(&mut buffer));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+mod ctrl_c {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    };
+
+    const SIGINT: i32 = 2;
+    const SIG_DFL: usize = 0;
+
+    // a signal handler can't capture anything; the prompt only ever has one session
+    static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+    unsafe extern "C" {
+        fn signal(signum: i32, handler: usize) -> usize;
+    }
+
+    extern "C" fn on_sigint(_signum: i32) {
+        if let Some(interrupt) = INTERRUPT.get() {
+            interrupt.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // restores the default handler when dropped
+    pub struct Guard;
+
+    pub fn interrupt(handle: Arc<AtomicBool>) -> Guard {
+        let _ = INTERRUPT.set(handle);
+        // SAFETY: the handler only does an atomic store
+        unsafe { signal(SIGINT, on_sigint as extern "C" fn(i32) as usize) };
+        Guard
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            // SAFETY: SIG_DFL is always a valid disposition
+            unsafe { signal(SIGINT, SIG_DFL) };
+        }
+    }
+}
+
+// elsewhere Ctrl-C keeps ending the whole session
+#[cfg(not(unix))]
+mod ctrl_c {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    pub struct Guard;
+
+    pub fn interrupt(_handle: Arc<AtomicBool>) -> Guard {
+        Guard
+    }
+}
@@ -1,84 +1,323 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io};
 
 use crate::{
     ast::expression::Expr,
     error::RuntimeSignal,
     interpreter::{
-        stmt::{FunctionDefinition, Stmt},
+        stmt::{ClassDefinition, FunctionDefinition, Stmt},
         Interpreter,
     },
+    lint::{Lint, LintConfig, Lints, Warning},
     scanner::token::Token,
 };
 
-pub struct Resolver {
-    interpreter: Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
 }
 
-impl Resolver {
-    pub fn new(interpreter: Interpreter) -> Self {
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function, // a declared function, method or lambda
+}
+
+// a local scope; each declaration takes the next slot of the environment it runs in
+#[derive(Default)]
+struct Scope {
+    names: HashMap<String, Binding>,
+    slots: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Binding {
+    defined: bool,
+    slot: usize,
+    line: usize,                // where it was declared
+    declaration: Option<usize>, // into Symbols::declarations, when they are recorded
+}
+
+// which declaration every name in a file refers to, for editor tooling
+#[derive(Debug, Default)]
+pub struct Symbols {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: Token,
+    pub kind: DeclarationKind,
+    pub global: bool, // at the top level of the file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeclarationKind {
+    Variable,
+    Function,
+    Class,
+    Parameter,
+    CatchVariable,
+    Import,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: Token,
+    // None for names the file never declares, e.g. natives or typos
+    pub declaration: Option<usize>,
+}
+
+// globals are looked up by name when the code runs, so a reference can precede the
+// declaration; they are matched up once the whole file has been seen
+#[derive(Default)]
+struct SymbolTable {
+    symbols: Symbols,
+    globals: HashMap<String, usize>, // the first top-level declaration of each name
+    unresolved: Vec<usize>,          // references that may name a global
+}
+
+// for resolving code that won't run, e.g. in tools; resolution only fills its tables
+pub(crate) fn throwaway_interpreter() -> Interpreter {
+    Interpreter::with_output(Box::new(io::sink()), Box::new(io::sink()))
+}
+
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    scopes: Vec<Scope>,
+    current_class: ClassType,
+    current_function: FunctionType,
+    loops: Vec<Option<String>>, // labels of the enclosing loops, innermost last
+    symbols: Option<SymbolTable>,
+    lints: Option<Lints>,
+    errors: Vec<RuntimeSignal>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
         Resolver {
             interpreter,
             scopes: Vec::new(),
+            current_class: ClassType::None,
+            current_function: FunctionType::None,
+            loops: Vec::new(),
+            symbols: None,
+            lints: None,
+            errors: Vec::new(),
+        }
+    }
+
+    // also records declarations and references, see 'into_symbols'
+    pub fn with_symbols(interpreter: &'a mut Interpreter) -> Self {
+        Resolver {
+            symbols: Some(SymbolTable::default()),
+            ..Self::new(interpreter)
+        }
+    }
+
+    // also checks the code for the lints enabled in 'config', see 'into_warnings'
+    pub fn with_lints(interpreter: &'a mut Interpreter, config: LintConfig) -> Self {
+        Resolver {
+            lints: Some(Lints::new(config)),
+            ..Self::with_symbols(interpreter)
+        }
+    }
+
+    // the warnings for everything resolved so far; empty unless built with 'with_lints'
+    pub fn into_warnings(mut self) -> Vec<Warning> {
+        let lints = self.lints.take();
+        let symbols = self.into_symbols();
+        lints.map_or_else(Vec::new, |lints| lints.finish(&symbols))
+    }
+
+    // everything resolved so far; empty unless built with 'with_symbols'
+    pub fn into_symbols(self) -> Symbols {
+        let Some(mut table) = self.symbols else {
+            return Symbols::default();
+        };
+        for reference in table.unresolved {
+            let reference = &mut table.symbols.references[reference];
+            reference.declaration = table.globals.get(&reference.name.lexeme).copied();
+        }
+        table.symbols
+    }
+
+    // every static error in 'statements' is returned, in the order they were found
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<(), Vec<RuntimeSignal>> {
+        if let Some(lints) = &mut self.lints {
+            lints.check_unreachable(statements);
         }
+        for stmt in statements {
+            self.resolve_stmt(stmt);
+        }
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // resolution carries on past an error, so one run reports all of them
+    fn error(&mut self, token: &Token, message: String) {
+        self.errors.push(RuntimeSignal::static_error_at(token, message));
     }
 
-    fn resolve_stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeSignal> {
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Block(stmts) => self.resolve_block(stmts),
             Stmt::Var(name, initializer) => self.resolve_var_stmt(name, initializer),
             Stmt::Function(fun_def) => {
-                self.declare(&fun_def.name);
+                self.declare(&fun_def.name, DeclarationKind::Function);
                 self.define(&fun_def.name);
                 self.resolve_function_stmt(fun_def)
             }
+            Stmt::Class(class_def) => self.resolve_class_stmt(class_def),
             Stmt::Expression(expr) => self.resolve_expr(expr),
             Stmt::If(if_conditions) => {
-                self.resolve_expr(&if_conditions.condition)?;
-                self.resolve_stmt(&if_conditions.then_branch)?;
+                if let Some(lints) = &mut self.lints {
+                    lints.check_condition(&if_conditions.keyword, &if_conditions.condition, false);
+                }
+                self.resolve_expr(&if_conditions.condition);
+                self.resolve_stmt(&if_conditions.then_branch);
                 if let Some(else_branch) = &if_conditions.else_branch {
-                    self.resolve_stmt(else_branch)?
+                    self.resolve_stmt(else_branch)
+                }
+            }
+            Stmt::Print(_, expr) => self.resolve_expr(expr),
+            Stmt::Export(declaration) => {
+                let name = declaration
+                    .declared_name()
+                    .expect("the parser only exports declarations");
+                self.require_top_level(name, "export");
+                self.resolve_stmt(declaration)
+            }
+            Stmt::Import(import) => {
+                self.require_top_level(&import.keyword, "import");
+                for name in import.names.iter().flatten() {
+                    self.declare(name, DeclarationKind::Import);
+                }
+            }
+            Stmt::Throw(_, value) => self.resolve_expr(value),
+            Stmt::Try(try_stmt) => {
+                self.resolve_block(&try_stmt.body);
+                if let Some(catch) = &try_stmt.catch {
+                    self.begin_scope();
+                    self.declare(&catch.name, DeclarationKind::CatchVariable);
+                    self.define(&catch.name);
+                    self.resolve_block(&catch.body);
+                    self.end_scope();
+                }
+                if let Some(finally) = &try_stmt.finally {
+                    self.resolve_block(finally);
                 }
-                Ok(())
             }
-            Stmt::Print(expr) => self.resolve_expr(expr),
-            Stmt::Return(_, value) => {
+            Stmt::Return(keyword, value) => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code".into());
+                }
                 if let Some(expr) = value {
-                    self.resolve_expr(expr)?
+                    self.resolve_expr(expr)
                 }
-                Ok(())
             }
             Stmt::While(while_conditions) => {
-                self.resolve_expr(&while_conditions.condition)?;
-                self.resolve_stmt(&while_conditions.stmt_body)
+                if let Some(lints) = &mut self.lints {
+                    lints.check_condition(
+                        &while_conditions.keyword,
+                        &while_conditions.condition,
+                        true,
+                    );
+                }
+                self.resolve_expr(&while_conditions.condition);
+
+                let label = while_conditions.label.as_ref().map(|t| t.lexeme.clone());
+                self.loops.push(label);
+                self.resolve_stmt(&while_conditions.stmt_body);
+                self.loops.pop();
+
+                if let Some(increment) = &while_conditions.increment {
+                    self.resolve_expr(increment);
+                }
+            }
+            Stmt::Break(keyword, label) | Stmt::Continue(keyword, label) => {
+                self.resolve_loop_jump(keyword, label)
             }
-            _ => todo!(),
         }
     }
 
-    fn resolve_function_stmt(&mut self, fun_def: &FunctionDefinition) -> Result<(), RuntimeSignal> {
+    fn resolve_loop_jump(&mut self, keyword: &Token, label: &Option<Token>) {
+        match label {
+            None if self.loops.is_empty() => self.error(
+                keyword,
+                format!("Can't use '{}' outside of a loop", keyword.lexeme),
+            ),
+            Some(label)
+                if !self
+                    .loops
+                    .iter()
+                    .any(|l| l.as_deref() == Some(label.lexeme.as_str())) =>
+            {
+                self.error(
+                    label,
+                    format!("No enclosing loop labeled '{}'", label.lexeme),
+                )
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_class_stmt(&mut self, class_def: &ClassDefinition) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(&class_def.name, DeclarationKind::Class);
+        self.define(&class_def.name);
+
+        self.begin_scope();
+        self.declare_name("this".into(), class_def.name.line, None);
+        self.define_name("this");
+
+        for method in &class_def.methods {
+            self.resolve_function_stmt(method);
+        }
+
+        self.end_scope();
+        self.current_class = enclosing_class;
+    }
+
+    fn resolve_function_stmt(&mut self, fun_def: &FunctionDefinition) {
+        // loops outside the function can't be targeted from inside it
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_function = self.current_function;
+        self.current_function = FunctionType::Function;
+
         self.begin_scope();
         for param in &fun_def.params {
-            self.declare(param);
+            self.declare(param, DeclarationKind::Parameter);
             self.define(param);
         }
 
-        self.resolve_block(&fun_def.body)?;
+        self.resolve_block(&fun_def.body);
         self.end_scope();
-        Ok(())
+        self.loops = enclosing_loops;
+        self.current_function = enclosing_function;
     }
 
-    fn resolve_expr(&mut self, expr: &Expr) -> Result<(), RuntimeSignal> {
+    fn resolve_expr(&mut self, expr: &Expr) {
+        self.interpreter.reserve_expr_ids(expr.id() + 1);
         match expr {
             Expr::Variable { token, .. } => self.resolve_var_expr(token, expr),
             Expr::Assignment { name, value, .. } => self.resolve_assign_expr(name, value, expr),
             Expr::Binary {
                 id: _,
                 left_expr,
-                operator: _,
+                operator,
                 right_expr,
             } => {
-                self.resolve_expr(left_expr)?;
+                if let Some(lints) = &mut self.lints {
+                    lints.check_comparison(left_expr, operator, right_expr);
+                }
+                self.resolve_expr(left_expr);
                 self.resolve_expr(right_expr)
             }
             Expr::Call {
@@ -87,21 +326,58 @@ impl Resolver {
                 paren: _,
                 arguments,
             } => {
-                self.resolve_expr(callee)?;
+                self.resolve_expr(callee);
                 for arg in arguments {
-                    self.resolve_expr(arg)?;
+                    self.resolve_expr(arg);
+                }
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.resolve_expr(element);
                 }
-                Ok(())
+            }
+            Expr::Lambda { fun_def, .. } => self.resolve_function_stmt(fun_def),
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index)
+            }
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value)
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object)
+            }
+            Expr::This { keyword, .. } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class".into());
+                    return;
+                }
+                self.resolve_var_local(expr, keyword);
             }
             Expr::Grouping { expression, .. } => self.resolve_expr(expression),
-            Expr::Literal { .. } => Ok(()),
+            Expr::Literal { .. } => {}
             Expr::Logical {
                 id: _,
                 left,
                 operator: _,
                 right,
             } => {
-                self.resolve_expr(left)?;
+                self.resolve_expr(left);
                 self.resolve_expr(right)
             }
             Expr::Unary {
@@ -112,74 +388,192 @@ impl Resolver {
         }
     }
 
-    fn resolve_assign_expr(
-        &mut self,
-        name: &Token,
-        value: &Expr,
-        expr: &Expr,
-    ) -> Result<(), RuntimeSignal> {
-        self.resolve_expr(value)?;
-        self.resolve_var_local(expr, name)?;
-        Ok(())
+    fn resolve_assign_expr(&mut self, name: &Token, value: &Expr, expr: &Expr) {
+        self.resolve_expr(value);
+        let declaration = self.resolve_var_local(expr, name);
+
+        if let Some(table) = &self.symbols
+            && let Some(lints) = &mut self.lints
+            && let Some(index) = declaration.or_else(|| table.globals.get(&name.lexeme).copied())
+            && table.symbols.declarations[index].kind == DeclarationKind::Function
+        {
+            lints.warn(
+                Lint::FunctionAssignment,
+                name,
+                format!("assignment to function '{}'", name.lexeme),
+            );
+        }
     }
 
-    fn resolve_var_expr(&mut self, name: &Token, expr: &Expr) -> Result<(), RuntimeSignal> {
-        if let Some(top_scope) = self.scopes.last_mut() && let Some(_) = top_scope.get(&name.lexeme) {
-            return Err(RuntimeSignal::static_error(name.line, "Can't read local variable in own initializer".into()));
-        } else {
-            self.resolve_var_local(expr, name)?;
+    fn resolve_var_expr(&mut self, name: &Token, expr: &Expr) {
+        if let Some(top_scope) = self.scopes.last()
+            && top_scope.names.get(&name.lexeme).is_some_and(|binding| !binding.defined)
+        {
+            self.error(name, "Can't read local variable in own initializer".into());
+            return;
+        }
+        let declaration = self.resolve_var_local(expr, name);
+        if let Some(lints) = &mut self.lints
+            && let Some(declaration) = declaration
+        {
+            lints.read(declaration);
         }
-        Ok(())
     }
 
-    fn resolve_var_local(&mut self, expr: &Expr, name: &Token) -> Result<(), RuntimeSignal> {
-        for (i, scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(expr, i);
+    // the declaration the name refers to, if it is a local one and they are being recorded
+    fn resolve_var_local(&mut self, expr: &Expr, name: &Token) -> Option<usize> {
+        let local = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| Some((depth, *scope.names.get(&name.lexeme)?)));
+
+        match local {
+            Some((depth, binding)) => {
+                self.interpreter.resolve(expr, depth, binding.slot);
+                // 'this' has no declaration of its own
+                if binding.declaration.is_some() {
+                    self.record_reference(name, binding.declaration);
+                }
+                binding.declaration
+            }
+            None => {
+                self.record_reference(name, None);
+                None
             }
         }
-        Ok(())
     }
 
-    fn resolve_block(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeSignal> {
+    fn record_reference(&mut self, name: &Token, declaration: Option<usize>) {
+        if let Some(table) = &mut self.symbols {
+            if declaration.is_none() {
+                table.unresolved.push(table.symbols.references.len());
+            }
+            table.symbols.references.push(Reference {
+                name: name.clone(),
+                declaration,
+            });
+        }
+    }
+
+    // modules are loaded relative to the file doing the importing, which is only
+    // well defined while its top level runs
+    fn require_top_level(&mut self, token: &Token, what: &str) {
+        if !self.scopes.is_empty() {
+            self.error(token, format!("Can only {what} at the top level of a file"));
+        }
+    }
+
+    fn resolve_block(&mut self, stmts: &[Stmt]) {
+        if let Some(lints) = &mut self.lints {
+            lints.check_unreachable(stmts);
+        }
         self.begin_scope();
         for stmt in stmts {
-            self.resolve_stmt(stmt)?;
+            self.resolve_stmt(stmt);
         }
         self.end_scope();
-        Ok(())
     }
 
-    fn resolve_var_stmt(
-        &mut self,
-        name: &Token,
-        initializer: &Option<Expr>,
-    ) -> Result<(), RuntimeSignal> {
-        self.declare(name);
+    fn resolve_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) {
+        self.declare(name, DeclarationKind::Variable);
         if let Some(expr) = initializer {
-            self.resolve_expr(expr)?;
+            self.resolve_expr(expr);
         }
         self.define(name);
-        Ok(())
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(Scope::default());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
     }
 
-    fn declare(&mut self, name: &Token) {
-        if let Some(top_scope) = self.scopes.last_mut() {
-            top_scope.insert(name.lexeme.clone(), false);
+    // globals can be redeclared, e.g. by each line typed into the REPL; locals can't
+    fn declare(&mut self, name: &Token, kind: DeclarationKind) {
+        let global = self.scopes.is_empty();
+        if !global {
+            self.check_duplicate(name);
+            self.check_shadowing(name);
+        }
+        let declaration = self.symbols.as_mut().map(|table| {
+            let index = table.symbols.declarations.len();
+            table.symbols.declarations.push(Declaration {
+                name: name.clone(),
+                kind,
+                global,
+            });
+            if global {
+                table.globals.entry(name.lexeme.clone()).or_insert(index);
+            }
+            index
+        });
+        self.declare_name(name.lexeme.clone(), name.line, declaration);
+    }
+
+    fn check_duplicate(&mut self, name: &Token) {
+        let Some(earlier) = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.names.get(&name.lexeme))
+        else {
+            return;
+        };
+        let message = format!(
+            "'{}' is already declared in this scope, on line {}",
+            name.lexeme, earlier.line
+        );
+        self.error(name, message);
+    }
+
+    // a local that hides a name from an enclosing scope, or a global declared before it
+    fn check_shadowing(&mut self, name: &Token) {
+        let (Some(table), Some(lints)) = (&self.symbols, &mut self.lints) else {
+            return;
+        };
+        let outer = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(&name.lexeme)?.declaration)
+            .or_else(|| table.globals.get(&name.lexeme).copied());
+        if let Some(outer) = outer {
+            let line = table.symbols.declarations[outer].name.line;
+            lints.warn(
+                Lint::Shadowing,
+                name,
+                format!("'{}' shadows the declaration on line {line}", name.lexeme),
+            );
         }
     }
 
     fn define(&mut self, name: &Token) {
+        self.define_name(&name.lexeme);
+    }
+
+    // a duplicate local is an error, but still takes a fresh slot like the runtime would give it
+    fn declare_name(&mut self, name: String, line: usize, declaration: Option<usize>) {
         if let Some(top_scope) = self.scopes.last_mut() {
-            top_scope.insert(name.lexeme.clone(), true);
+            let binding = Binding {
+                defined: false,
+                slot: top_scope.slots,
+                line,
+                declaration,
+            };
+            top_scope.slots += 1;
+            top_scope.names.insert(name, binding);
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(binding) = self
+            .scopes
+            .last_mut()
+            .and_then(|top_scope| top_scope.names.get_mut(name))
+        {
+            binding.defined = true;
         }
     }
 }
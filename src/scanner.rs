@@ -4,7 +4,7 @@ pub mod token_type;
 use crate::{
     error::RuntimeSignal,
     scanner::{
-        token::{Literal, Token},
+        token::{Literal, Token, Trivia, TriviaKind},
         token_type::TokenType,
     },
 };
@@ -13,19 +13,27 @@ use crate::{
 fn get_keyword(to_find: &str) -> Option<TokenType> {
     match to_find {
         "and" => Some(TokenType::And),
+        "break" => Some(TokenType::Break),
+        "catch" => Some(TokenType::Catch),
         "class" => Some(TokenType::Class),
+        "continue" => Some(TokenType::Continue),
         "else" => Some(TokenType::Else),
+        "export" => Some(TokenType::Export),
         "false" => Some(TokenType::False),
+        "finally" => Some(TokenType::Finally),
         "fun" => Some(TokenType::Fun),
         "for" => Some(TokenType::For),
         "if" => Some(TokenType::If),
+        "import" => Some(TokenType::Import),
         "nil" => Some(TokenType::Nil),
         "or" => Some(TokenType::Or),
         "print" => Some(TokenType::Print),
         "return" => Some(TokenType::Return),
         "super" => Some(TokenType::Super),
         "this" => Some(TokenType::This),
+        "throw" => Some(TokenType::Throw),
         "true" => Some(TokenType::True),
+        "try" => Some(TokenType::Try),
         "var" => Some(TokenType::Var),
         "while" => Some(TokenType::While),
         _ => None,
@@ -36,6 +44,9 @@ struct Cursor {
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize, // byte offset where the current line begins
+    start_line: usize,
+    start_column: usize,
 }
 
 pub struct Scanner {
@@ -43,6 +54,9 @@ pub struct Scanner {
     tokens: Vec<Token>,
     cursor: Cursor,
     errors: Vec<RuntimeSignal>,
+    // comments and blank lines, only recorded for tools that reprint the source
+    keep_trivia: bool,
+    trivia: Vec<Trivia>,
 }
 
 impl Scanner {
@@ -54,14 +68,34 @@ impl Scanner {
                 start: 0,
                 current: 0,
                 line: 1,
+                line_start: 0,
+                start_line: 1,
+                start_column: 1,
             },
             errors: Vec::new(),
+            keep_trivia: false,
+            trivia: Vec::new(),
         }
     }
 
+    // a scanner that also records comments and blank lines, see 'trivia'
+    pub fn with_trivia(source: String) -> Self {
+        Scanner {
+            keep_trivia: true,
+            ..Self::new(source)
+        }
+    }
+
+    // in source order; empty unless built with 'with_trivia'
+    pub fn trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
     pub fn scan_tokens(&mut self) -> (Vec<Token>, Vec<RuntimeSignal>) {
         while !self.is_at_end() {
             self.cursor.start = self.cursor.current;
+            self.cursor.start_line = self.cursor.line;
+            self.cursor.start_column = self.cursor.current - self.cursor.line_start + 1;
             self.scan_token();
         }
 
@@ -70,6 +104,8 @@ impl Scanner {
             "".to_string(),
             None,
             self.cursor.line,
+            self.cursor.current - self.cursor.line_start + 1,
+            self.cursor.current,
         ));
 
         (self.tokens.clone(), self.errors.clone())
@@ -84,10 +120,10 @@ impl Scanner {
         let c = match self.advance() {
             Some(c) => c,
             None => {
-                self.errors.push(RuntimeSignal::static_error(
-                    self.cursor.line,
-                    format!("failed to get u8 at index {}", self.cursor.current),
-                ));
+                self.errors.push(self.error_at_token_start(format!(
+                    "failed to get u8 at index {}",
+                    self.cursor.current
+                )));
                 return;
             }
         };
@@ -98,6 +134,9 @@ impl Scanner {
             b')' => self.extract_and_add_token(TokenType::RightParen, None),
             b'{' => self.extract_and_add_token(TokenType::LeftBrace, None),
             b'}' => self.extract_and_add_token(TokenType::RightBrace, None),
+            b'[' => self.extract_and_add_token(TokenType::LeftBracket, None),
+            b']' => self.extract_and_add_token(TokenType::RightBracket, None),
+            b':' => self.extract_and_add_token(TokenType::Colon, None),
             b',' => self.extract_and_add_token(TokenType::Comma, None),
             b'.' => self.extract_and_add_token(TokenType::Dot, None),
             b'-' => self.extract_and_add_token(TokenType::Minus, None),
@@ -113,13 +152,20 @@ impl Scanner {
                     while self.peek() != Some(b'\n') && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_trivia(TriviaKind::LineComment);
                 } else if self.match_current(b'*') {
                     self.skip_bulk_comments();
                 } else {
                     self.extract_and_add_token(TokenType::Slash, None);
                 }
             }
-            b'\n' => self.cursor.line += 1,
+            b'\n' => {
+                let line = &self.source[self.cursor.line_start..self.cursor.start];
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    self.add_trivia(TriviaKind::BlankLine);
+                }
+                self.new_line();
+            }
 
             // =============== LITERAL, IDENTIFIERS, AND WHITESPACE ==================
             b'"' => self.handle_string(),
@@ -130,10 +176,8 @@ impl Scanner {
                 } else if c.is_ascii_alphanumeric() {
                     self.handle_identifier();
                 } else {
-                    self.errors.push(RuntimeSignal::static_error(
-                        self.cursor.line,
-                        format!("unexpected token: '{}'", c as char),
-                    ));
+                    let error = self.error_at_token_start(format!("unexpected token: '{}'", c as char));
+                    self.errors.push(error);
                 }
             }
         }
@@ -164,32 +208,71 @@ impl Scanner {
         c
     }
 
+    // call after consuming a '\n'
+    fn new_line(&mut self) {
+        self.cursor.line += 1;
+        self.cursor.line_start = self.cursor.current;
+    }
+
+    // error pointing at the first character of the token being scanned
+    fn error_at_token_start(&self, message: String) -> RuntimeSignal {
+        RuntimeSignal::static_error_span(
+            self.cursor.start_line,
+            self.cursor.start_column,
+            self.cursor.start,
+            1,
+            message,
+        )
+    }
+
     fn skip_bulk_comments(&mut self) {
         // looking for */ pattern
-        let original_line = self.cursor.line;
         while (self.peek() != Some(b'*') || self.peek_next() != Some(b'/')) && !self.is_at_end() {
-            if self.peek() == Some(b'\n') {
-                self.cursor.line += 1;
+            if self.advance() == Some(b'\n') {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.errors.push(RuntimeSignal::static_error(
-                original_line,
-                "unterminated bulk comment".to_string(),
-            ));
+            let error = self.error_at_token_start("unterminated bulk comment".to_string());
+            self.errors.push(error);
             return;
         }
 
         // skip */
         self.advance();
         self.advance();
+        self.add_trivia(TriviaKind::BlockComment);
+    }
+
+    // the text scanned since the token start, minus any line ending
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        if !self.keep_trivia {
+            return;
+        }
+        let text = match kind {
+            TriviaKind::BlankLine => String::new(),
+            _ => String::from_utf8_lossy(&self.source[self.cursor.start..self.cursor.current])
+                .trim_end_matches('\r')
+                .to_string(),
+        };
+        self.trivia.push(Trivia {
+            kind,
+            text,
+            line: self.cursor.start_line,
+            offset: self.cursor.start,
+        });
     }
 
     fn add_token(&mut self, lexeme: String, token_type: TokenType, literal: Option<Literal>) {
-        self.tokens
-            .push(Token::new(token_type, lexeme, literal, self.cursor.line));
+        self.tokens.push(Token::new(
+            token_type,
+            lexeme,
+            literal,
+            self.cursor.start_line,
+            self.cursor.start_column,
+            self.cursor.start,
+        ));
     }
 
     fn extract_and_add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
@@ -198,12 +281,7 @@ impl Scanner {
         };
 
         let lexeme_str = str.to_string();
-        self.tokens.push(Token::new(
-            token_type,
-            lexeme_str,
-            literal,
-            self.cursor.line,
-        ));
+        self.add_token(lexeme_str, token_type, literal);
     }
 
     fn add_conditional_token(&mut self, expected: u8, matched: TokenType, unmatched: TokenType) {
@@ -231,21 +309,20 @@ impl Scanner {
 
     fn handle_string(&mut self) {
         let line_before = self.cursor.line;
+        let line_start_before = self.cursor.line_start;
         while self.peek() != Some(b'"') && !self.is_at_end() {
-            if self.peek() == Some(b'\n') {
-                self.cursor.line += 1;
+            if self.advance() == Some(b'\n') {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.errors.push(RuntimeSignal::static_error(
-                self.cursor.line,
-                "unterminated string".into(),
-            ));
+            let error = self.error_at_token_start("unterminated string".into());
+            self.errors.push(error);
 
             self.cursor.current = self.cursor.start + 1;
             self.cursor.line = line_before;
+            self.cursor.line_start = line_start_before;
         }
 
         self.advance();
@@ -311,8 +388,14 @@ impl Scanner {
                     "Invalid UTF-8 found on line {}, bytes: {:?}, with error: {}",
                     self.cursor.line, bytes, err
                 );
-                self.errors
-                    .push(RuntimeSignal::static_error(self.cursor.line, msg));
+                let error = RuntimeSignal::static_error_span(
+                    self.cursor.start_line,
+                    self.cursor.start_column,
+                    self.cursor.start,
+                    bytes.len(),
+                    msg,
+                );
+                self.errors.push(error);
                 return None;
             }
         };
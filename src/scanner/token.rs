@@ -14,6 +14,25 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line: usize,
+    pub column: usize, // 1-based, in bytes from the start of the line
+    pub offset: usize, // byte offset into the source
+    pub length: usize, // byte length of the lexeme
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    LineComment,
+    BlockComment,
+    BlankLine,
+}
+
+// source text the parser never sees; kept so the source can be reprinted faithfully
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,  // the comment including its delimiters; empty for blank lines
+    pub line: usize,   // where the trivia starts
+    pub offset: usize, // byte offset into the source
 }
 
 impl fmt::Display for Token {
@@ -32,12 +51,18 @@ impl Token {
         lexeme: String,
         literal: Option<Literal>,
         line: usize,
+        column: usize,
+        offset: usize,
     ) -> Self {
+        let length = lexeme.len();
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            offset,
+            length,
         }
     }
 }
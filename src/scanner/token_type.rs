@@ -7,6 +7,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -32,19 +35,27 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
+    Export,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -58,6 +69,9 @@ impl fmt::Display for TokenType {
             TokenType::RightParen => ")",
             TokenType::LeftBrace => "{",
             TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Colon => ":",
             TokenType::Comma => ",",
             TokenType::Dot => ".",
             TokenType::Minus => "-",
@@ -77,19 +91,27 @@ impl fmt::Display for TokenType {
             TokenType::String => "string",
             TokenType::Number => "number",
             TokenType::And => "and",
+            TokenType::Break => "break",
+            TokenType::Catch => "catch",
             TokenType::Class => "class",
+            TokenType::Continue => "continue",
             TokenType::Else => "else",
+            TokenType::Export => "export",
             TokenType::False => "false",
+            TokenType::Finally => "finally",
             TokenType::Fun => "fun",
             TokenType::For => "for",
             TokenType::If => "if",
+            TokenType::Import => "import",
             TokenType::Nil => "nil",
             TokenType::Or => "or",
             TokenType::Print => "print",
             TokenType::Return => "return",
             TokenType::Super => "super",
             TokenType::This => "this",
+            TokenType::Throw => "throw",
             TokenType::True => "true",
+            TokenType::Try => "try",
             TokenType::Var => "var",
             TokenType::While => "while",
             TokenType::EOF => "eof",
@@ -1,9 +1,12 @@
 #![allow(dead_code)]
 
 use std::{
+    cell::RefCell,
     fs,
-    path::PathBuf,
-    process::{Command, Output},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    rc::Rc,
     sync::atomic::{AtomicU64, Ordering},
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -11,7 +14,8 @@ use std::{
 use rlox::{
     ast::parser::Parser,
     error::RuntimeSignal,
-    interpreter::{stmt::Stmt, Interpreter},
+    interpreter::{stmt::Stmt, Backend, Interpreter},
+    resolver::Resolver,
     scanner::{token_type::TokenType, Scanner},
 };
 
@@ -37,29 +41,135 @@ pub fn is_static_error(err: &RuntimeSignal) -> bool {
 }
 
 pub fn run_with_interpreter(source: &str) {
+    let mut interpreter = Interpreter::new();
+    run_in(&mut interpreter, source);
+}
+
+pub fn run_in(interpreter: &mut Interpreter, source: &str) {
     let (statements, errors) = parse_source(source);
     assert!(errors.is_empty(), "expected parse success");
 
-    let mut interpreter = Interpreter::new();
+    Resolver::new(interpreter)
+        .resolve(&statements)
+        .expect("expected resolve success");
     interpreter.interpret(&statements);
 }
 
+// runs the binary on both backends, which must agree, and returns the tree-walker's output
 pub fn run_cli(source: &str) -> Output {
     let path = temp_lox_file();
     fs::write(&path, source).expect("failed to write temp lox file");
 
-    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
-        .arg(&path)
-        .output()
-        .expect("failed to run rlox binary");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .args(args)
+            .arg(&path)
+            .output()
+            .expect("failed to run rlox binary")
+    };
+    let tree_walk = run(&[]);
+    let bytecode = run(&["--vm"]);
+
+    let _ = fs::remove_file(&path);
+    assert_eq!(
+        (&tree_walk.status, stdout_text(&tree_walk), stderr_text(&tree_walk)),
+        (&bytecode.status, stdout_text(&bytecode), stderr_text(&bytecode)),
+        "backends disagree on:\n{source}"
+    );
+    tree_walk
+}
+
+pub fn run_repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rlox binary");
+
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(input.as_bytes())
+        .expect("failed to write repl input");
+
+    child.wait_with_output().expect("failed to wait for rlox")
+}
+
+// repl stdout with the '> ' and '. ' prompts stripped
+pub fn repl_lines(input: &str) -> Vec<String> {
+    let output = run_repl(input);
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(strip_prompts)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn strip_prompts(mut line: &str) -> &str {
+    while let Some(rest) = line.strip_prefix("> ").or_else(|| line.strip_prefix(". ")) {
+        line = rest;
+    }
+    line.trim()
+}
+
+// in-memory Write sink the test keeps a handle to after handing it to the interpreter
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
 
-    let _ = fs::remove_file(path);
-    output
+impl SharedBuffer {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub const BACKENDS: [Backend; 2] = [Backend::TreeWalk, Backend::Bytecode];
+
+// an interpreter on 'backend' along with handles to what it writes to stdout and stderr
+pub fn captured_interpreter(backend: Backend) -> (Interpreter, SharedBuffer, SharedBuffer) {
+    let stdout = SharedBuffer::default();
+    let stderr = SharedBuffer::default();
+    let mut interpreter =
+        Interpreter::with_output(Box::new(stdout.clone()), Box::new(stderr.clone()));
+    interpreter.set_backend(backend);
+    (interpreter, stdout, stderr)
+}
+
+// runs the source in-process on both backends, which must agree; returns (stdout, stderr)
+pub fn run_captured(source: &str) -> (String, String) {
+    let tree_walk = run_captured_on(Backend::TreeWalk, source);
+    let bytecode = run_captured_on(Backend::Bytecode, source);
+    assert_eq!(tree_walk, bytecode, "backends disagree on:\n{source}");
+    tree_walk
+}
+
+pub fn run_captured_on(backend: Backend, source: &str) -> (String, String) {
+    let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+    run_in(&mut interpreter, source);
+    (stdout.text(), stderr.text())
 }
 
 pub fn runtime_lines(source: &str) -> Vec<String> {
-    let output = run_cli(source);
-    stdout_runtime_lines(&output)
+    let (stdout, _stderr) = run_captured(source);
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 pub fn stdout_runtime_lines(output: &Output) -> Vec<String> {
@@ -79,6 +189,10 @@ pub fn stdout_runtime_lines(output: &Output) -> Vec<String> {
         .collect()
 }
 
+pub fn stdout_text(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
 pub fn stderr_text(output: &Output) -> String {
     String::from_utf8_lossy(&output.stderr).to_string()
 }
@@ -93,3 +207,29 @@ fn temp_lox_file() -> PathBuf {
 
     std::env::temp_dir().join(format!("rlox-test-{}-{now}-{id}.lox", std::process::id()))
 }
+
+// writes each (relative path, source) pair under a fresh temp directory
+pub fn temp_module_dir(files: &[(&str, &str)]) -> PathBuf {
+    let dir = temp_lox_file().with_extension("");
+    for (name, source) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).expect("failed to create module dir");
+        fs::write(path, source).expect("failed to write module file");
+    }
+    dir
+}
+
+// runs a script file in-process on both backends, so imports resolve relative to it
+pub fn run_script(path: &Path) -> (String, String) {
+    let source = fs::read_to_string(path).expect("failed to read script");
+    let run_on = |backend| {
+        let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+        interpreter.set_script_path(path);
+        run_in(&mut interpreter, &source);
+        (stdout.text(), stderr.text())
+    };
+    let tree_walk = run_on(Backend::TreeWalk);
+    let bytecode = run_on(Backend::Bytecode);
+    assert_eq!(tree_walk, bytecode, "backends disagree on {}", path.display());
+    tree_walk
+}
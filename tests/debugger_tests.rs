@@ -0,0 +1,323 @@
+mod common;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs,
+    io::{Cursor, Write},
+    process::{Command, Stdio},
+    rc::Rc,
+};
+
+use rlox::{
+    debug::dap,
+    interpreter::{
+        debugger::{DebugFrontend, Debugger, Pause, PauseReason, Resume, StackFrame},
+        Interpreter,
+    },
+    lsp::{json::Json, read_message},
+    Lox,
+};
+
+use common::{stdout_text, temp_module_dir, SharedBuffer};
+
+const SCRIPT: &str = "\
+var total = 0;
+fun add(n) {
+  var doubled = n * 2;
+  total = total + doubled;
+  return doubled;
+}
+for (var i = 0; i < 2; i = i + 1) {
+  add(i);
+}
+print total;
+";
+
+// what the script looked like at one pause
+#[derive(Debug, Clone, PartialEq)]
+struct Stop {
+    reason: PauseReason,
+    line: usize,
+    frames: Vec<StackFrame>,
+    variables: Vec<Vec<String>>, // 'name = value' per scope, innermost first
+}
+
+// resumes with a fixed list of actions, then continues, remembering every pause
+#[derive(Clone, Default)]
+struct Scripted {
+    actions: Rc<RefCell<VecDeque<Resume>>>,
+    stops: Rc<RefCell<Vec<Stop>>>,
+}
+
+impl DebugFrontend for Scripted {
+    fn paused(&mut self, pause: Pause) -> Resume {
+        self.stops.borrow_mut().push(Stop {
+            reason: pause.reason,
+            line: pause.line,
+            frames: pause.frames,
+            variables: pause
+                .scopes
+                .iter()
+                .map(|scope| {
+                    scope
+                        .variables
+                        .iter()
+                        .map(|(name, value)| match value {
+                            Some(value) => format!("{name} = {value}"),
+                            None => format!("{name} = <uninitialized>"),
+                        })
+                        .collect()
+                })
+                .collect(),
+        });
+        self.actions
+            .borrow_mut()
+            .pop_front()
+            .unwrap_or(Resume::Continue)
+    }
+}
+
+fn debug(source: &str, breakpoints: &[usize], actions: &[Resume]) -> (Vec<Stop>, String, String) {
+    let stdout = SharedBuffer::default();
+    let stderr = SharedBuffer::default();
+    let mut lox = Lox::with_interpreter(Interpreter::with_output(
+        Box::new(stdout.clone()),
+        Box::new(stderr.clone()),
+    ));
+    let statements = lox.compile(source).expect("script should compile");
+
+    let frontend = Scripted::default();
+    frontend.actions.borrow_mut().extend(actions);
+    let mut debugger = Debugger::new(frontend.clone());
+    for line in breakpoints {
+        debugger.set_breakpoint(*line);
+    }
+    lox.interpreter().set_debugger(debugger);
+    lox.interpreter().interpret(&statements);
+
+    let stops = frontend.stops.borrow().clone();
+    (stops, stdout.text(), stderr.text())
+}
+
+fn lines(stops: &[Stop]) -> Vec<(PauseReason, usize)> {
+    stops.iter().map(|stop| (stop.reason, stop.line)).collect()
+}
+
+#[test]
+fn steps_in_over_and_out_of_calls() {
+    use PauseReason::*;
+    use Resume::*;
+
+    let (stops, stdout, _) = debug(
+        SCRIPT,
+        &[],
+        &[StepIn, StepIn, StepIn, StepIn, StepIn, StepOut, StepOver],
+    );
+    assert_eq!(
+        lines(&stops),
+        vec![
+            (Entry, 1),
+            (Step, 2),
+            (Step, 7),
+            (Step, 8),
+            (Step, 3), // into add
+            (Step, 4),
+            (Step, 8),  // out, on the next iteration
+            (Step, 10), // over the second call
+        ]
+    );
+    assert_eq!(stdout, "2\n");
+}
+
+#[test]
+fn breakpoints_pause_each_time_with_stack_and_scopes() {
+    let (stops, _, _) = debug(SCRIPT, &[4], &[Resume::Continue]);
+    assert_eq!(
+        lines(&stops),
+        vec![
+            (PauseReason::Entry, 1),
+            (PauseReason::Breakpoint, 4),
+            (PauseReason::Breakpoint, 4),
+        ]
+    );
+
+    let second = &stops[2];
+    assert_eq!(
+        second.frames,
+        vec![
+            StackFrame {
+                function: "add".into(),
+                line: 4
+            },
+            StackFrame {
+                function: "script".into(),
+                line: 8
+            },
+        ]
+    );
+    // the body's block, the parameters, then the globals without the natives
+    assert_eq!(
+        second.variables,
+        vec![
+            vec!["doubled = 2".to_string()],
+            vec!["n = 1".to_string()],
+            vec!["add = callable".to_string(), "total = 0".to_string()],
+        ]
+    );
+}
+
+#[test]
+fn stopping_abandons_the_script() {
+    let (stops, stdout, stderr) = debug(
+        "print 1;\ntry {\n  print 2;\n} finally {\n  print 3;\n}\n",
+        &[3],
+        &[Resume::Continue, Resume::Stop],
+    );
+    assert_eq!(stops.len(), 2);
+    assert_eq!(stdout, "1\n");
+    assert!(stderr.contains("stopped by the debugger"), "{stderr}");
+}
+
+#[test]
+fn console_runs_commands_from_stdin() {
+    let dir = temp_module_dir(&[("main.lox", SCRIPT)]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg("debug")
+        .arg(dir.join("main.lox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rlox binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"break 4\ncontinue\nbt\nprint n\nvars\nclear 4\nc\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let _ = fs::remove_dir_all(dir);
+
+    let stdout = stdout_text(&output);
+    for expected in [
+        "Paused on entry at line 1: var total = 0;",
+        "Breakpoint set on line 4",
+        "Breakpoint at line 4: total = total + doubled;",
+        "#0 [line 4] in add()\n#1 [line 8] in script()",
+        "(debug) n = 0",
+        "local:\n  doubled = 0\nenclosing 1:\n  n = 0\nglobals:\n  add = callable\n  total = 0",
+        "Breakpoint cleared on line 4",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {expected:?} in:\n{stdout}"
+        );
+    }
+    assert!(stdout.ends_with("(debug) 2\n"), "{stdout}");
+}
+
+#[test]
+fn debug_adapter_serves_a_session() {
+    let dir = temp_module_dir(&[("main.lox", SCRIPT)]);
+    let program = dir.join("main.lox").display().to_string();
+    let requests = [
+        ("initialize", Json::object([])),
+        (
+            "launch",
+            Json::object([("program", program.as_str().into())]),
+        ),
+        (
+            "setBreakpoints",
+            Json::object([(
+                "breakpoints",
+                Json::Array(vec![Json::object([("line", 3.into())])]),
+            )]),
+        ),
+        ("configurationDone", Json::object([])),
+        ("stackTrace", Json::object([("threadId", 1.into())])),
+        (
+            "variables",
+            Json::object([("variablesReference", 2.into())]),
+        ),
+        ("evaluate", Json::object([("expression", "total".into())])),
+        ("next", Json::object([("threadId", 1.into())])),
+        (
+            "setBreakpoints",
+            Json::object([("breakpoints", Json::Array(vec![]))]),
+        ),
+        ("continue", Json::object([("threadId", 1.into())])),
+        ("disconnect", Json::object([])),
+    ];
+    let mut input = String::new();
+    for (seq, (command, arguments)) in requests.into_iter().enumerate() {
+        let body = Json::object([
+            ("seq", (seq + 1).into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments),
+        ])
+        .to_string();
+        input.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+    }
+
+    let output = SharedBuffer::default();
+    dap::serve(Cursor::new(input.into_bytes()), output.clone()).unwrap();
+    let _ = fs::remove_dir_all(dir);
+
+    let text = output.text();
+    let mut reader = Cursor::new(text.as_bytes());
+    let mut messages = Vec::new();
+    while let Some(body) = read_message(&mut reader).unwrap() {
+        messages.push(Json::parse(&body).unwrap());
+    }
+
+    let failed: Vec<_> = messages
+        .iter()
+        .filter(|message| message.get("success").as_bool() == Some(false))
+        .collect();
+    assert!(failed.is_empty(), "{failed:?}");
+
+    let events: Vec<_> = messages
+        .iter()
+        .filter(|message| message.get("type").as_str() == Some("event"))
+        .map(|message| {
+            let body = message.get("body");
+            let detail = body
+                .get("reason")
+                .as_str()
+                .or(body.get("output").as_str())
+                .unwrap_or("");
+            format!("{} {detail}", message.get("event").as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            "initialized ",
+            "stopped breakpoint",
+            "stopped step",
+            "output 2\n",
+            "terminated ",
+        ]
+    );
+
+    let response = |command: &str| {
+        messages
+            .iter()
+            .find(|message| message.get("command").as_str() == Some(command))
+            .map(|message| message.get("body").clone())
+            .unwrap()
+    };
+    let frames = response("stackTrace");
+    let top = &frames.get("stackFrames").as_array().unwrap()[0];
+    assert_eq!(top.get("name").as_str(), Some("add"));
+    assert_eq!(top.get("line").as_usize(), Some(3));
+    assert_eq!(
+        response("variables").get("variables").as_array().unwrap()[0]
+            .get("value")
+            .as_str(),
+        Some("0")
+    );
+    assert_eq!(response("evaluate").get("result").as_str(), Some("0"));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use rlox::{formatter::format_source, scanner::Scanner};
+
+use common::{run_captured, stdout_text, temp_module_dir};
+
+const MESSY: &str = "// counts down\n\n\nfun count(n){while(n>0){print n;n=n-1;}return -n;}\nvar m={\"k\":[1,2]};   // a map\nif(m[\"k\"][0]==1){print \"one\";}else{print \"other\";}\n\n{\n}\nprint count(2);\n";
+
+#[test]
+fn normalises_indentation_spacing_and_braces() {
+    let formatted = format_source(MESSY).expect("should format");
+    assert_eq!(
+        formatted,
+        "// counts down\n\
+         \n\
+         fun count(n) {\n  while (n > 0) {\n    print n;\n    n = n - 1;\n  }\n  return -n;\n}\n\
+         var m = {\"k\": [1, 2]}; // a map\n\
+         if (m[\"k\"][0] == 1) {\n  print \"one\";\n} else {\n  print \"other\";\n}\n\
+         \n\
+         {}\n\
+         print count(2);\n"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent_and_keeps_the_program() {
+    let sources = [
+        MESSY,
+        "class A{init(x){this.x=x;}\n/* getter */\nget(){return this.x;}}\nprint A(3).get();",
+        "var f=fun(a){return fun(){return a;};};print f(1)();",
+        "try{throw \"e\";}catch(e){print e;}finally{print -1- -2;}",
+        "outer: for(var i=0;i<3;i=i+1){for(;;){break outer;}}\nprint !true;",
+    ];
+    for source in sources {
+        let formatted = format_source(source).expect("should format");
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+
+        let lexemes = |text: &str| {
+            let (tokens, _) = Scanner::new(text.to_string()).scan_tokens();
+            tokens
+                .into_iter()
+                .map(|token| (token.token_type, token.lexeme))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lexemes(source), lexemes(&formatted));
+        assert_eq!(run_captured(source), run_captured(&formatted));
+    }
+}
+
+#[test]
+fn keeps_trailing_own_line_and_block_comments() {
+    let source = "{ // opens\n  // alone\n    a = 1;  /* after */\n}\n";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "{ // opens\n  // alone\n  a = 1; /* after */\n}\n"
+    );
+}
+
+#[test]
+fn refuses_to_format_code_that_does_not_parse() {
+    let errors = format_source("print (1;").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(format_source("print \"open;").is_err());
+}
+
+#[test]
+fn fmt_command_checks_and_rewrites_files() {
+    let dir = temp_module_dir(&[("messy.lox", MESSY), ("tidy.lox", "print 1;\n")]);
+    let messy = dir.join("messy.lox");
+    let tidy = dir.join("tidy.lox");
+    let fmt = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .arg("fmt")
+            .args(args)
+            .arg(&messy)
+            .arg(&tidy)
+            .output()
+            .expect("failed to run rlox binary")
+    };
+
+    let check = fmt(&["--check"]);
+    assert_eq!(check.status.code(), Some(1));
+    assert_eq!(stdout_text(&check), format!("{}\n", messy.display()));
+    assert_eq!(fs::read_to_string(&messy).unwrap(), MESSY);
+
+    assert_eq!(fmt(&[]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(&messy).unwrap(),
+        format_source(MESSY).unwrap()
+    );
+    assert_eq!(fmt(&["--check"]).status.code(), Some(0));
+
+    let _ = fs::remove_dir_all(dir);
+}
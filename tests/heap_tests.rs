@@ -0,0 +1,85 @@
+mod common;
+
+use common::{captured_interpreter, run_in, BACKENDS};
+
+#[test]
+fn functions_stored_in_their_own_scope_are_collected() {
+    for backend in BACKENDS {
+        let (mut interpreter, _stdout, _stderr) = captured_interpreter(backend);
+        run_in(
+            &mut interpreter,
+            "for (var i = 0; i < 50; i = i + 1) {
+                fun loop() { return loop; }
+                var self = [loop];
+            }",
+        );
+
+        let before = interpreter.heap_stats();
+        let freed = interpreter.collect_garbage();
+        let after = interpreter.heap_stats();
+
+        assert!(freed >= 50, "{backend:?} freed {freed}");
+        assert!(
+            after.tracked + 50 <= before.tracked,
+            "{backend:?}: {before:?} -> {after:?}"
+        );
+        assert_eq!(after.collections, 1);
+        assert_eq!(after.freed, freed);
+        assert_eq!(after.allocated, 0);
+    }
+}
+
+#[test]
+fn collecting_keeps_reachable_values_intact() {
+    let source = "
+        fun counter() {
+            var count = 0;
+            fun increment() { count = count + 1; return count; }
+            return increment;
+        }
+        class Node {
+            init(next) { this.next = next; this.me = this; }
+            depth() { if (this.next == nil) return 1; return 1 + this.next.depth(); }
+        }
+        var tick = counter();
+        var nodes = [];
+        for (var i = 0; i < 20; i = i + 1) {
+            tick();
+            fun unused() { return unused; }
+            push(nodes, Node(nil));
+        }
+        var chain = Node(Node(Node(nil)));
+        print tick();
+        print chain.me.depth();
+        print len(nodes);
+    ";
+
+    for backend in BACKENDS {
+        let (mut interpreter, stdout, _stderr) = captured_interpreter(backend);
+        interpreter.set_gc_threshold(1);
+        run_in(&mut interpreter, source);
+
+        assert_eq!(stdout.text(), "21\n3\n20\n", "{backend:?}");
+        assert!(interpreter.heap_stats().collections > 0, "{backend:?}");
+        assert!(interpreter.heap_stats().freed >= 20, "{backend:?}");
+    }
+}
+
+#[test]
+fn collections_run_once_the_threshold_is_reached() {
+    for backend in BACKENDS {
+        let (mut interpreter, _stdout, _stderr) = captured_interpreter(backend);
+        assert_eq!(interpreter.heap_stats().collections, 0);
+        interpreter.set_gc_threshold(10);
+        assert_eq!(interpreter.heap_stats().threshold, 10);
+
+        run_in(
+            &mut interpreter,
+            "for (var i = 0; i < 30; i = i + 1) { fun f() { return f; } }",
+        );
+
+        let stats = interpreter.heap_stats();
+        assert!(stats.collections >= 2, "{backend:?}: {stats:?}");
+        assert!(stats.allocated < 10, "{backend:?}: {stats:?}");
+    }
+}
@@ -1,6 +1,12 @@
 mod common;
 
-use common::{run_cli, runtime_lines, stderr_text, stdout_runtime_lines};
+use std::io;
+
+use common::{
+    captured_interpreter, run_captured, run_cli, run_in, runtime_lines, stderr_text,
+    stdout_runtime_lines, stdout_text, BACKENDS,
+};
+use rlox::interpreter::Interpreter;
 
 #[test]
 fn interprets_statement_and_expression_happy_paths() {
@@ -99,3 +105,507 @@ fn functions_support_recursion() {
 
     assert_eq!(lines, vec!["8"]);
 }
+
+#[test]
+fn classes_support_methods_initializers_and_fields() {
+    let lines = runtime_lines(
+        r#"
+        class Counter {
+          init(start) {
+            this.count = start;
+          }
+          increment() {
+            this.count = this.count + 1;
+            return this;
+          }
+        }
+        var c = Counter(1);
+        print c.increment().increment().count;
+        c.label = 7;
+        print c.label;
+        var inc = c.increment;
+        inc();
+        print c.count;
+        print c;
+        print Counter;
+        "#,
+    );
+
+    assert_eq!(lines, vec!["3", "7", "4", "Counter instance", "Counter"]);
+}
+
+#[test]
+fn class_error_paths_report_runtime_error_type() {
+    for source in [
+        "class A {} print A().missing;",
+        "var x = 1; print x.field;",
+        "var x = 1; x.field = 2;",
+        "class A { init(a) {} } A();",
+    ] {
+        let (_stdout, stderr) = run_captured(source);
+        assert!(stderr.contains("Runtime Error"));
+    }
+}
+
+#[test]
+fn closures_bind_to_lexical_scope() {
+    let lines = runtime_lines(
+        r#"
+        var a = "global";
+        {
+          fun show() {
+            print a;
+          }
+          show();
+          var a = "block";
+          show();
+        }
+
+        fun makeCounter() {
+          var i = 0;
+          fun count() {
+            i = i + 1;
+            return i;
+          }
+          return count;
+        }
+        var counter = makeCounter();
+        counter();
+        print counter();
+        "#,
+    );
+
+    assert_eq!(lines, vec!["\"global\"", "\"global\"", "2"]);
+}
+
+#[test]
+fn resolver_errors_stop_execution() {
+    for source in [
+        "{ var a = 1; { var a = a; } } print 1;",
+        "print this; print 1;",
+    ] {
+        let output = run_cli(source);
+        assert!(stdout_runtime_lines(&output)
+            .iter()
+            .any(|line| line.starts_with("Static Error")));
+        assert!(!stdout_runtime_lines(&output).contains(&"1".to_string()));
+    }
+}
+
+#[test]
+fn top_level_returns_and_duplicate_locals_are_all_reported_before_running() {
+    let output = run_cli("print 1;\nreturn;\nfun f() {\n  var n = 1;\n  var n = 2;\n}\n");
+    let stdout = stdout_text(&output);
+
+    assert!(!stdout_runtime_lines(&output).contains(&"1".to_string()));
+    assert!(
+        stdout.contains("Static Error: Can't return from top-level code\n --> "),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(".lox:2:1\n  |\n2 | return;\n  | ^^^^^^\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Static Error: 'n' is already declared in this scope, on line 4"));
+    assert!(stdout.contains(".lox:5:7\n"), "{stdout}");
+}
+
+#[test]
+fn nil_is_a_readable_value_distinct_from_uninitialized() {
+    let (stdout, stderr) = run_captured(
+        r#"
+        var x = nil;
+        print x;
+        fun nothing() {}
+        var r = nothing();
+        print r;
+        var later;
+        later = nil;
+        print later;
+        var never;
+        print never;
+        "#,
+    );
+
+    assert_eq!(stdout, "NIL\nNIL\nNIL\n");
+    assert!(stderr.contains("unitialized variable 'never'"));
+}
+
+#[test]
+fn errors_render_source_line_with_caret_underline() {
+    let output = run_cli("var a = 1;\nprint a + (true - 2);\n");
+    let stderr = stderr_text(&output);
+
+    assert!(stderr.contains("Runtime Error: '-' operation attempted on non numeric types"));
+    assert!(stderr.contains(".lox:2:17"));
+    assert!(stderr.contains("2 | print a + (true - 2);"));
+    assert!(stderr.contains("  |                 ^\n"));
+}
+
+#[test]
+fn runtime_errors_in_calls_print_a_traceback() {
+    let (_stdout, stderr) = run_captured(
+        r#"fun fib(n) {
+          if (n <= 1) return n + nil;
+          return fib(n - 2) + fib(n - 1);
+        }
+        class Runner { go() { return fib(3); } }
+        print Runner().go();
+        "#,
+    );
+
+    assert!(stderr.contains(
+        "Traceback (most recent call last):\n  [line 6] in go()\n  [line 5] in fib()\n  [line 3] in fib()"
+    ));
+}
+
+#[test]
+fn output_sinks_capture_print_and_diagnostics_in_process() {
+    let (stdout, stderr) = run_captured("print 1; print \"two\"; print -nil; print 3;");
+
+    assert_eq!(stdout, "1\n\"two\"\n");
+    assert!(stderr.starts_with("Runtime Error on [line 1:] {-} operation attempted on non numeric type"));
+}
+
+#[test]
+fn output_can_be_discarded() {
+    let mut interpreter = Interpreter::with_output(Box::new(io::sink()), Box::new(io::sink()));
+    run_in(&mut interpreter, "print 1; print missing;");
+}
+
+#[test]
+fn break_and_continue_control_loops() {
+    let lines = runtime_lines(
+        r#"
+        for (var i = 0; i < 5; i = i + 1) {
+          if (i == 1) continue;
+          if (i == 4) break;
+          print i;
+        }
+
+        outer: for (var i = 0; i < 3; i = i + 1) {
+          var j = 0;
+          while (true) {
+            j = j + 1;
+            if (j > 2) continue outer;
+            if (i == 2) break outer;
+            print i * 10 + j;
+          }
+        }
+        "#,
+    );
+
+    assert_eq!(lines, vec!["0", "2", "3", "1", "2", "11", "12"]);
+}
+
+#[test]
+fn loop_jumps_outside_loops_are_static_errors() {
+    for source in [
+        "break;",
+        "continue;",
+        "while (true) { fun f() { break; } }",
+        "a: while (true) { break b; }",
+    ] {
+        let output = run_cli(source);
+        assert!(stdout_runtime_lines(&output)[0].starts_with("Static Error"));
+    }
+}
+
+#[test]
+fn lists_support_indexing_and_natives() {
+    let lines = runtime_lines(
+        r#"
+        var xs = [1, 2, 3];
+        xs[1] = 20;
+        print xs[0] + xs[1];
+        print push(xs, 4);
+        print pop(xs);
+        insert(xs, 0, 0);
+        print xs;
+        print slice(xs, 1, 3);
+        print slice(xs, 2);
+        var alias = xs;
+        push(alias, 5);
+        print len(xs);
+        print [[7]][0][0];
+        "#,
+    );
+
+    assert_eq!(
+        lines,
+        vec!["21", "4", "4", "[0, 1, 20, 3]", "[1, 20]", "[20, 3]", "5", "7"]
+    );
+}
+
+#[test]
+fn self_referential_lists_print_without_recursing_forever() {
+    let output = run_cli(
+        "var a = [1, 2];\npush(a, a);\nprint a;\nvar b = [a, [a]];\nprint b;\nprint len(a);\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_text(&output),
+        "[1, 2, [...]]\n[[1, 2, [...]], [[1, 2, [...]]]]\n3\n"
+    );
+}
+
+#[test]
+fn list_error_paths_report_runtime_error_type() {
+    for source in [
+        "[1][1];",
+        "[1][-1];",
+        "[1][0.5];",
+        "[1][\"0\"];",
+        "var x = 1; x[0];",
+        "pop([]);",
+        "insert([], 2, 1);",
+        "slice([1, 2], 2, 1);",
+        "len(1);",
+    ] {
+        let (_stdout, stderr) = run_captured(source);
+        assert!(stderr.contains("Runtime Error"), "{source}");
+    }
+}
+
+#[test]
+fn maps_support_keyed_access_and_natives() {
+    let lines = runtime_lines(
+        r#"
+        var m = {"a": 1, 2: "two", nil: false};
+        m["b"] = m["a"] + 1;
+        m[2] = "deux";
+        print m;
+        m[-0] = "zero";
+        print m[0];
+        remove(m, 0);
+        print keys(m);
+        print values(m);
+        print has(m, "b");
+        print remove(m, "a");
+        print remove(m, "a");
+        print has(m, "a");
+        print len(m);
+        var alias = m;
+        alias[true] = 1;
+        print len(m);
+        "#,
+    );
+
+    assert_eq!(
+        lines,
+        vec![
+            "{\"a\": 1, 2: \"deux\", nil: false, \"b\": 2}",
+            "\"zero\"",
+            "[\"a\", 2, nil, \"b\"]",
+            "[1, \"deux\", false, 2]",
+            "true",
+            "1",
+            "NIL",
+            "false",
+            "3",
+            "4",
+        ]
+    );
+}
+
+#[test]
+fn self_referential_maps_print_without_recursing_forever() {
+    let output = run_cli(
+        "var m = {\"a\": 1};\nm[\"self\"] = m;\nprint m;\nvar xs = [m];\nm[\"xs\"] = xs;\nprint xs;\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_text(&output),
+        "{\"a\": 1, \"self\": {...}}\n[{\"a\": 1, \"self\": {...}, \"xs\": [...]}]\n"
+    );
+}
+
+#[test]
+fn map_error_paths_report_runtime_error_type() {
+    for source in [
+        "({})[\"missing\"];",
+        "var m = {}; m[0/0] = 1;",
+        "({0/0: 1});",
+        "var m = {}; m[[]] = 1;",
+        "var m = {}; m[clock] = 1;",
+        "has({}, {});",
+        "keys([]);",
+    ] {
+        let (_stdout, stderr) = run_captured(source);
+        assert!(stderr.contains("Runtime Error"), "{source}");
+    }
+}
+
+#[test]
+fn lambdas_are_first_class_closures() {
+    let lines = runtime_lines(
+        r#"
+        fun map(xs, f) {
+            var out = [];
+            for (var i = 0; i < len(xs); i = i + 1) push(out, f(xs[i]));
+            return out;
+        }
+        var factor = 10;
+        print map([1, 2, 3], fun (x) { return x * factor; });
+
+        fun counter() {
+            var n = 0;
+            return fun () { n = n + 1; return n; };
+        }
+        var next = counter();
+        next();
+        print next();
+
+        fun (a, b) { print a + b; }(1, 2);
+        "#,
+    );
+
+    assert_eq!(lines, vec!["[10, 20, 30]", "2", "3"]);
+}
+
+#[test]
+fn runtime_errors_and_thrown_values_can_be_caught() {
+    let lines = runtime_lines(
+        r#"
+        fun add(a, b) { return a + b; }
+        try {
+            add(1, nil);
+        } catch (e) {
+            print e.message;
+            print e.line;
+        }
+        try { add(1); } catch (e) { print e.message; }
+        try { throw {"code": 7}; } catch (e) { print e["code"]; } finally { print "done"; }
+        try {
+            try { throw "inner"; } finally { print "cleanup"; }
+        } catch (e) {
+            print e;
+        }
+        fun early() {
+            try { return 1; } finally { print "finally runs"; }
+        }
+        print early();
+        print "still running";
+        "#,
+    );
+
+    assert_eq!(
+        lines,
+        vec![
+            "\"+ operation attempted on binary in which neither are of type String or Number\"",
+            "2",
+            "\"Expected 2 arguments, but got 1\"",
+            "7",
+            "\"done\"",
+            "\"cleanup\"",
+            "\"inner\"",
+            "\"finally runs\"",
+            "1",
+            "\"still running\"",
+        ]
+    );
+}
+
+#[test]
+fn uncaught_throw_reports_a_runtime_error() {
+    let (stdout, stderr) = run_captured("print 1;\nthrow \"bad\";\nprint 2;");
+    assert_eq!(stdout.trim(), "1");
+    assert!(stderr.contains("Runtime Error"));
+    assert!(stderr.contains("Uncaught \"bad\""));
+
+    let (_stdout, stderr) = run_captured("try { throw 1; } catch (e) { throw e; }");
+    assert!(stderr.contains("Uncaught 1"));
+}
+
+#[test]
+fn finally_runs_on_every_jump_out_of_a_try() {
+    let lines = runtime_lines(
+        r#"
+        fun f() {
+          for (var i = 0; i < 4; i = i + 1) {
+            try {
+              if (i == 1) continue;
+              if (i == 2) return i * 10;
+              print i;
+            } finally {
+              print "fin";
+            }
+          }
+        }
+        print f();
+        outer: while (true) {
+          while (true) {
+            try { break outer; } finally { print "bye"; }
+          }
+        }
+        fun swallow() { try { throw 1; } finally { return 2; } }
+        print swallow();
+        var fns = [];
+        for (var j = 0; j < 3; j = j + 1) { var k = j; push(fns, fun () { return k; }); }
+        print fns[0]() + fns[1]() + fns[2]();
+        "#,
+    );
+
+    assert_eq!(lines, vec!["0", "\"fin\"", "\"fin\"", "\"fin\"", "20", "\"bye\"", "2", "3"]);
+}
+
+#[test]
+fn locals_are_found_by_slot_across_nested_scopes() {
+    let lines = runtime_lines(
+        r#"
+        var g = "global";
+        {
+          var a = 1;
+          var b = 2;
+          fun sum(x, y) {
+            var z = x + y;
+            { var w = z * 10; return w + a + b; }
+          }
+          print sum(3, 4);
+          {
+            var a = 100;
+            print a + b;
+          }
+          print a;
+          try { throw b; } catch (e) { var inner = e + a; print inner; }
+          class Box { init(v) { this.v = v; } get() { return this.v + a; } }
+          print Box(5).get();
+          print g;
+        }
+        "#,
+    );
+
+    assert_eq!(lines, vec!["73", "102", "1", "3", "6", "\"global\""]);
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow_error_not_a_crash() {
+    let output = run_cli("fun down(n) {\n  return down(n + 1);\n}\ndown(0);\nprint 1;\n");
+    let stderr = stderr_text(&output);
+
+    assert!(output.status.success());
+    assert!(stderr.contains("Runtime Error: stack overflow"), "{stderr}");
+    assert!(stderr.contains(
+        "  [line 4] in down()\n  [line 2] in down()\n  [line 2] in down()\n  [line 2] in down()\n  [Previous line repeated 996 more times]"
+    ));
+}
+
+#[test]
+fn call_depth_limit_is_configurable_and_catchable() {
+    let source = r#"
+        var reached = 0;
+        fun down(n) { reached = n; return down(n + 1); }
+        try { down(1); } catch (e) { print e.message; }
+        print reached;
+        fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); }
+        print count(19);
+    "#;
+
+    for backend in BACKENDS {
+        let (mut interpreter, stdout, _stderr) = captured_interpreter(backend);
+        interpreter.set_max_call_depth(20);
+        run_in(&mut interpreter, source);
+
+        assert_eq!(stdout.text(), "\"stack overflow\"\n20\n19\n", "{backend:?}");
+    }
+}
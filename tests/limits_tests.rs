@@ -0,0 +1,156 @@
+mod common;
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::atomic::Ordering,
+    thread,
+    time::Duration,
+};
+
+use common::{captured_interpreter, run_in, stderr_text, stdout_text, BACKENDS};
+
+#[test]
+fn statement_budget_stops_runaway_loops() {
+    for backend in BACKENDS {
+        let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+        interpreter.set_statement_budget(Some(1000));
+        run_in(
+            &mut interpreter,
+            r#"
+            print "start";
+            try { while (true) {} } catch (e) { print "caught"; } finally { print "finally"; }
+            "#,
+        );
+
+        // the budget stays exhausted, so neither the catch nor the finally gets to run
+        assert_eq!(stdout.text(), "\"start\"\n", "{backend:?}");
+        assert_eq!(
+            stderr.text().trim(),
+            "Budget Exhausted: statement budget of 1000 exhausted",
+            "{backend:?}"
+        );
+    }
+}
+
+#[test]
+fn backends_spend_the_same_budget() {
+    let source = r#"
+        fun fib(n) { if (n <= 1) return n; return fib(n - 2) + fib(n - 1); }
+        class Counter {
+            init() { this.count = 0; }
+            tick() { this.count = this.count + 1; print this.count; }
+        }
+        var counter = Counter();
+        for (var i = 0; i < 3; i = i + 1) {
+            counter.tick();
+            try { throw i; } catch (e) { print e; } finally { print fib(i + 3); }
+        }
+        {
+            var double = fun (x) { return x * 2; };
+            print double(21);
+        }
+        print "done";
+    "#;
+
+    for budget in 1..120 {
+        let outputs: Vec<(String, String)> = BACKENDS
+            .map(|backend| {
+                let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+                interpreter.set_statement_budget(Some(budget));
+                run_in(&mut interpreter, source);
+                (stdout.text(), stderr.text())
+            })
+            .into();
+        assert_eq!(outputs[0], outputs[1], "budget {budget}");
+    }
+}
+
+#[test]
+fn budgets_and_time_limits_apply_to_each_run() {
+    for backend in BACKENDS {
+        let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+        interpreter.set_statement_budget(Some(3));
+        interpreter.set_time_limit(Some(Duration::from_secs(60)));
+        run_in(&mut interpreter, "print 1; print 2; print 3;");
+        run_in(&mut interpreter, "print 4; print 5; print 6;");
+
+        assert_eq!(stdout.text(), "1\n2\n3\n4\n5\n6\n", "{backend:?}");
+        assert_eq!(stderr.text(), "", "{backend:?}");
+    }
+}
+
+#[test]
+fn time_limit_stops_infinite_loops() {
+    for backend in BACKENDS {
+        let (mut interpreter, _stdout, stderr) = captured_interpreter(backend);
+        interpreter.set_time_limit(Some(Duration::from_millis(20)));
+        run_in(&mut interpreter, "fun spin() { while (true) {} }\nspin();");
+
+        let stderr = stderr.text();
+        assert!(
+            stderr.starts_with("Deadline Exceeded on [line 2]: time limit of 20ms exceeded"),
+            "{backend:?}: {stderr}"
+        );
+        assert!(stderr.contains("[line 2] in spin()"), "{backend:?}: {stderr}");
+    }
+}
+
+#[test]
+fn interrupt_handle_stops_a_running_script() {
+    for backend in BACKENDS {
+        let (mut interpreter, stdout, stderr) = captured_interpreter(backend);
+        let interrupt = interpreter.interrupt_handle();
+        let trigger = {
+            let interrupt = interrupt.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                interrupt.store(true, Ordering::Relaxed);
+            })
+        };
+        run_in(&mut interpreter, "while (true) {}");
+        trigger.join().unwrap();
+
+        assert_eq!(
+            stderr.text().trim(),
+            "Interrupted: execution interrupted",
+            "{backend:?}"
+        );
+
+        // runs stay stopped until the host clears the flag
+        run_in(&mut interpreter, "print 1;");
+        interrupt.store(false, Ordering::Relaxed);
+        run_in(&mut interpreter, "print 2;");
+        assert_eq!(stdout.text(), "2\n", "{backend:?}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_in_the_repl_stops_the_running_input_only() {
+    for args in [&[][..], &["--vm"][..]] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn rlox binary");
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        stdin.write_all(b"while (true) {}\n").unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        let status = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .expect("failed to run kill");
+        assert!(status.success());
+        stdin.write_all(b"print \"after\";\n").unwrap();
+        drop(stdin);
+
+        let output = child.wait_with_output().expect("failed to wait for rlox");
+        assert!(output.status.success(), "{args:?}");
+        assert!(stderr_text(&output).contains("Interrupted: execution interrupted"));
+        assert!(stdout_text(&output).contains("\"after\""), "{args:?}");
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use rlox::lint::{lint_source, Lint, LintConfig};
+
+use common::{stderr_text, stdout_text, temp_module_dir};
+
+// (lint, line, column) of every warning, in source order
+fn warnings(source: &str, config: &LintConfig) -> Vec<(Lint, usize, usize)> {
+    lint_source(source, config)
+        .expect("source should compile")
+        .iter()
+        .map(|warning| (warning.lint, warning.line, warning.column))
+        .collect()
+}
+
+#[test]
+fn reports_unused_locals_and_shadowed_bindings() {
+    let source = "\
+var total = 0;
+fun add(n, extra) {
+  var total = n;
+  var spare;
+  spare = 1;
+  return total;
+}
+{
+  var i = 0;
+  {
+    var i = 1;
+    print i;
+  }
+}
+print add;
+";
+    assert_eq!(
+        warnings(source, &LintConfig::default()),
+        vec![
+            (Lint::UnusedVariable, 2, 12),
+            (Lint::Shadowing, 3, 7),
+            (Lint::UnusedVariable, 4, 7), // assigned, but never read
+            (Lint::UnusedVariable, 9, 7),
+            (Lint::Shadowing, 11, 9),
+        ]
+    );
+}
+
+#[test]
+fn reports_unreachable_code_and_function_assignment() {
+    let source = "\
+fun first(list) {
+  for (var i = 0; i < len(list); i = i + 1) {
+    if (list[i]) { return i; } else { continue; }
+    print i;
+  }
+  return nil;
+  print list;
+}
+first = nil;
+";
+    assert_eq!(
+        warnings(source, &LintConfig::default()),
+        vec![
+            (Lint::UnreachableCode, 3, 20),
+            (Lint::UnreachableCode, 6, 3),
+            (Lint::FunctionAssignment, 9, 1),
+        ]
+    );
+}
+
+#[test]
+fn reports_literal_comparisons_and_constant_conditions() {
+    let source = "\
+var n = 3;
+if (n * 2 == \"6\") print 1;
+if (-n < nil) print 2;
+if (n == 6) print 3;
+if (\"text\") print 4;
+while (nil or !true) {}
+while (true) { break; }
+for (;;) { break; }
+if (n > 1 and true) print 5;
+";
+    assert_eq!(
+        warnings(source, &LintConfig::default()),
+        vec![
+            (Lint::LiteralComparison, 2, 11),
+            (Lint::LiteralComparison, 3, 8),
+            (Lint::ConstantCondition, 5, 1),
+            (Lint::ConstantCondition, 6, 1),
+        ]
+    );
+
+    let messages: Vec<String> = lint_source(source, &LintConfig::default())
+        .unwrap()
+        .iter()
+        .map(|warning| warning.message.clone())
+        .collect();
+    assert_eq!(
+        messages[0],
+        "comparing a number with a string is always false"
+    );
+    assert_eq!(
+        messages[1],
+        "comparing a number with nil is a runtime error"
+    );
+    assert_eq!(messages[3], "this condition is always false");
+}
+
+#[test]
+fn lints_can_be_allowed_individually() {
+    let source = "{ var a = 1; { var a = 2; } }\nif (true) print 1;\n";
+    let mut config = LintConfig::default();
+    config.allow(Lint::UnusedVariable);
+    config.allow(Lint::ConstantCondition);
+    assert_eq!(warnings(source, &config), vec![(Lint::Shadowing, 1, 20)]);
+
+    config.warn(Lint::ConstantCondition);
+    config.allow(Lint::Shadowing);
+    assert_eq!(
+        warnings(source, &config),
+        vec![(Lint::ConstantCondition, 2, 1)]
+    );
+
+    // code that doesn't compile has errors instead of warnings
+    assert!(lint_source("print ;", &config).is_err());
+}
+
+#[test]
+fn warnings_are_printed_before_running_and_by_the_lint_command() {
+    let dir = temp_module_dir(&[(
+        "main.lox",
+        "fun f() {\n  return 1;\n  print 2;\n}\nprint f();\n",
+    )]);
+    let path = dir.join("main.lox");
+    let rlox = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .args(args)
+            .arg(&path)
+            .output()
+            .expect("failed to run rlox binary")
+    };
+
+    let run = rlox(&[]);
+    let stderr = stderr_text(&run);
+    assert_eq!(stdout_text(&run), "1\n");
+    assert!(
+        stderr.contains("Warning: code after this 'return' is unreachable [unreachable-code]"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("main.lox:2:3\n  |\n2 |   return 1;\n  |   ^^^^^^\n"),
+        "{stderr}"
+    );
+
+    let quiet = rlox(&["--allow", "unreachable-code"]);
+    assert_eq!(stderr_text(&quiet), "");
+
+    let lint = rlox(&["lint"]);
+    assert_eq!(lint.status.code(), Some(1));
+    assert!(stdout_text(&lint).contains("[unreachable-code]"));
+    let clean = rlox(&["--allow", "unreachable-code", "lint"]);
+    assert_eq!(clean.status.code(), Some(0));
+    assert_eq!(stdout_text(&clean), "");
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use common::{captured_interpreter, BACKENDS};
+use rlox::{
+    error::ErrorKind,
+    interpreter::{spawn_with_stack, values::Value, Backend, DEFAULT_MAX_CALL_DEPTH},
+    Lox,
+};
+
+#[test]
+fn eval_returns_value_of_trailing_expression() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::TreeWalk).0);
+
+    assert_eq!(lox.eval("var a = 20; a + 22;").unwrap(), Value::Number(42.0));
+    assert_eq!(lox.eval("print a;").unwrap(), Value::Nil);
+    assert_eq!(lox.eval("a == 20;").unwrap(), Value::Boolean(true));
+}
+
+#[test]
+fn eval_keeps_state_between_calls() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::TreeWalk).0);
+    lox.eval("fun square(n) { return n * n; }").unwrap();
+    lox.interpreter()
+        .define_native("seven", 0, |_, _| Ok(Value::Number(7.0)));
+
+    assert_eq!(lox.eval("square(seven());").unwrap(), Value::Number(49.0));
+}
+
+#[test]
+fn eval_reports_structured_static_errors() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::TreeWalk).0);
+    let errors = lox.eval("var a = 1;\nprint a +;\nprint (1;").unwrap_err();
+
+    assert!(!errors.is_empty());
+    assert!(errors.iter().all(|e| e.kind() == ErrorKind::Static));
+    assert_eq!(errors[0].line(), 2);
+    assert_eq!(errors[0].column(), Some(10));
+    assert_eq!(errors[0].offset(), Some(20));
+
+    // the scanner's errors, raised before there is a token
+    let errors = lox.eval("print 1;\nvar s = \"open;").unwrap_err();
+    assert_eq!(errors[0].offset(), Some(17));
+    let errors = lox.eval("print 1;\n  @").unwrap_err();
+    assert_eq!(errors[0].offset(), Some(11));
+}
+
+#[test]
+fn compile_collects_every_resolver_error() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::TreeWalk).0);
+    let errors = lox
+        .compile(
+            "return 1;\n{\n  var a = 1;\n  var a = 2;\n}\nfun f(x, x) { return x; }\nprint this;\nbreak;",
+        )
+        .unwrap_err();
+
+    let found: Vec<(usize, Option<usize>, &str)> = errors
+        .iter()
+        .map(|e| (e.line(), e.column(), e.message()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, Some(1), "Can't return from top-level code"),
+            (
+                4,
+                Some(7),
+                "'a' is already declared in this scope, on line 3"
+            ),
+            (
+                6,
+                Some(10),
+                "'x' is already declared in this scope, on line 6"
+            ),
+            (7, Some(7), "Can't use 'this' outside of a class"),
+            (8, Some(1), "Can't use 'break' outside of a loop"),
+        ]
+    );
+    assert!(errors.iter().all(|e| e.kind() == ErrorKind::Static));
+    assert_eq!(errors[0].length(), "return".len());
+
+    // returning from functions, lambdas and methods is fine, as is redeclaring a global
+    lox.compile(
+        "var g = 1;\nvar g = 2;\nfun f() { return fun () { return 1; }; }\nclass C { m() { return this; } }",
+    )
+    .unwrap();
+}
+
+#[test]
+fn eval_reports_structured_runtime_errors() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::TreeWalk).0);
+    let errors = lox.eval("var x = 1;\nx - \"s\";").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind(), ErrorKind::Runtime);
+    assert_eq!(errors[0].line(), 2);
+    assert_eq!(errors[0].offset(), Some(13));
+    assert_eq!(
+        errors[0].message(),
+        "'-' operation attempted on non numeric types"
+    );
+
+    let errors = lox.eval("print 1;
+  throw 2;").unwrap_err();
+    assert_eq!(errors[0].offset(), Some(11));
+    assert!(errors[0].thrown().is_some());
+}
+
+#[test]
+fn eval_runs_on_the_bytecode_backend() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::Bytecode).0);
+
+    lox.eval("class Point { init(x) { this.x = x; } }").unwrap();
+    assert_eq!(lox.eval("var p = Point(2); p.x * 21;").unwrap(), Value::Number(42.0));
+
+    let errors = lox.eval("p.y;").unwrap_err();
+    assert_eq!(errors[0].kind(), ErrorKind::Runtime);
+    assert_eq!(errors[0].message(), "Undefined property 'y'");
+}
+
+// recursion that is cut short at the default call depth, or earlier if the stack runs short
+const DEEP_RECURSION: &str = "
+    var reached = 0;
+    fun deep(n) {
+      reached = n;
+      if (n > 0) { while (true) { { var x = 1 + (2 * deep(n + 1)); return x; } } }
+    }
+    try { deep(1); } catch (e) { print e.message; }
+    reached;";
+
+#[test]
+fn eval_hits_the_default_call_depth_on_a_thread_with_room() {
+    let run = spawn_with_stack(256 * 1024 * 1024, || {
+        for backend in BACKENDS {
+            let (interpreter, stdout, _stderr) = captured_interpreter(backend);
+            let mut lox = Lox::with_interpreter(interpreter);
+
+            let errors = lox.eval("fun d(n) { return d(n + 1); } d(0);").unwrap_err();
+            assert_eq!(errors[0].message(), "stack overflow", "{backend:?}");
+            assert_eq!(errors[0].trace().len(), DEFAULT_MAX_CALL_DEPTH);
+
+            // a body that nests deeper takes more stack per call
+            let reached = lox.eval(DEEP_RECURSION).unwrap();
+            assert_eq!(
+                reached,
+                Value::Number(DEFAULT_MAX_CALL_DEPTH as f64),
+                "{backend:?}"
+            );
+            assert_eq!(stdout.text(), "\"stack overflow\"\n");
+        }
+    });
+    run.unwrap().join().unwrap();
+}
+
+#[test]
+fn deep_recursion_on_a_small_thread_is_a_catchable_stack_overflow() {
+    // test threads have 2MB of stack, less than the tree-walker needs for the default depth
+    for backend in BACKENDS {
+        let (interpreter, stdout, _stderr) = captured_interpreter(backend);
+        let mut lox = Lox::with_interpreter(interpreter);
+
+        let Value::Number(reached) = lox.eval(DEEP_RECURSION).unwrap() else {
+            panic!("expected a number");
+        };
+        assert!(reached > 1.0 && reached <= DEFAULT_MAX_CALL_DEPTH as f64, "{backend:?}");
+        assert_eq!(stdout.text(), "\"stack overflow\"\n", "{backend:?}");
+    }
+}
+
+#[test]
+fn too_many_locals_is_reported_where_the_slot_runs_out() {
+    let mut lox = Lox::with_interpreter(captured_interpreter(Backend::Bytecode).0);
+    // with the slot for the function itself, these fill every slot there is
+    let locals: String = (0..u16::MAX).map(|i| format!("var v{i};")).collect();
+
+    let errors = lox
+        .eval(&format!("fun f() {{\n{locals}\n  try {{}} finally {{}}\n}}"))
+        .unwrap_err();
+    assert_eq!(
+        errors[0].message(),
+        "Too many local variables in a function"
+    );
+    assert_eq!((errors[0].line(), errors[0].column()), (3, Some(3)));
+}
@@ -0,0 +1,293 @@
+use std::io::Cursor;
+
+use rlox::lsp::{self, json::Json, Server};
+
+const URI: &str = "file:///test.lox";
+
+fn open(server: &mut Server, text: &str) -> Json {
+    let replies = server.handle(&Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/didOpen".into()),
+        (
+            "params",
+            Json::object([(
+                "textDocument",
+                Json::object([
+                    ("uri", URI.into()),
+                    ("languageId", "lox".into()),
+                    ("version", 1.into()),
+                    ("text", text.into()),
+                ]),
+            )]),
+        ),
+    ]));
+    assert_eq!(replies.len(), 1);
+    assert_eq!(
+        replies[0].get("method").as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    replies[0].get("params").get("diagnostics").clone()
+}
+
+// 0-based line and character, as the client sends them
+fn request(server: &mut Server, method: &str, line: usize, character: usize) -> Json {
+    let replies = server.handle(&Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", 7.into()),
+        ("method", method.into()),
+        (
+            "params",
+            Json::object([
+                ("textDocument", Json::object([("uri", URI.into())])),
+                (
+                    "position",
+                    Json::object([("line", line.into()), ("character", character.into())]),
+                ),
+                (
+                    "context",
+                    Json::object([("includeDeclaration", true.into())]),
+                ),
+            ]),
+        ),
+    ]));
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].get("id").as_usize(), Some(7));
+    replies[0].get("result").clone()
+}
+
+// (line, start character, end character) of a single line range
+fn span(range: &Json) -> (usize, usize, usize) {
+    let start = range.get("start");
+    let end = range.get("end");
+    assert_eq!(start.get("line"), end.get("line"));
+    (
+        start.get("line").as_usize().unwrap(),
+        start.get("character").as_usize().unwrap(),
+        end.get("character").as_usize().unwrap(),
+    )
+}
+
+fn spans(locations: &Json) -> Vec<(usize, usize, usize)> {
+    locations
+        .as_array()
+        .expect("expected an array of locations")
+        .iter()
+        .map(|location| span(location.get("range")))
+        .collect()
+}
+
+#[test]
+fn diagnostics_come_from_the_scanner_parser_and_resolver() {
+    let mut server = Server::new();
+
+    let diagnostics = open(&mut server, "var a = 1;\nprint a +;\n");
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(span(diagnostics[0].get("range")).0, 1);
+    assert_eq!(diagnostics[0].get("severity").as_usize(), Some(1));
+
+    let diagnostics = open(&mut server, "fun f() {\n  var x = x;\n}\n");
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].get("message").as_str(),
+        Some("Can't read local variable in own initializer")
+    );
+    assert_eq!(span(diagnostics[0].get("range")), (1, 10, 11));
+
+    let diagnostics = open(&mut server, "var s = \"open;\n");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+
+    let diagnostics = open(&mut server, "print clock();\n");
+    assert_eq!(diagnostics, Json::Array(Vec::new()));
+}
+
+#[test]
+fn unfinished_documents_get_diagnostics_at_the_end() {
+    let mut server = Server::new();
+    for text in ["print (", "f(", "[", "print {", "x[", "var l = [1,", "var m = {1:"] {
+        let diagnostics = open(&mut server, text);
+        let diagnostics = diagnostics.as_array().unwrap();
+        assert!(!diagnostics.is_empty(), "{text}");
+        assert_eq!(
+            span(diagnostics[0].get("range")).1,
+            text.len(),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn definitions_and_references_follow_resolver_scopes() {
+    let mut server = Server::new();
+    let source = "\
+fun outer(n) {
+  var total = n;
+  {
+    var total = 2;
+    print total;
+  }
+  return helper(total);
+}
+fun helper(value) { return value; }
+print outer(1) + helper(2);
+";
+    assert_eq!(open(&mut server, source), Json::Array(Vec::new()));
+
+    // the inner 'total' shadows the outer one
+    let definition = request(&mut server, "textDocument/definition", 4, 11);
+    assert_eq!(definition.get("uri").as_str(), Some(URI));
+    assert_eq!(span(definition.get("range")), (3, 8, 13));
+    let definition = request(&mut server, "textDocument/definition", 6, 16);
+    assert_eq!(span(definition.get("range")), (1, 6, 11));
+
+    // a global used before its declaration
+    let definition = request(&mut server, "textDocument/definition", 6, 9);
+    assert_eq!(span(definition.get("range")), (8, 4, 10));
+
+    let references = request(&mut server, "textDocument/references", 8, 5);
+    assert_eq!(
+        spans(&references),
+        vec![(6, 9, 15), (8, 4, 10), (9, 17, 23)]
+    );
+    let references = request(&mut server, "textDocument/references", 0, 10);
+    assert_eq!(spans(&references), vec![(0, 10, 11), (1, 14, 15)]);
+
+    // natives and blank space have no declaration
+    assert_eq!(
+        request(&mut server, "textDocument/definition", 9, 0),
+        Json::Null
+    );
+    assert_eq!(
+        request(&mut server, "textDocument/references", 5, 1),
+        Json::Array(Vec::new())
+    );
+}
+
+#[test]
+fn hover_shows_the_declaration_site() {
+    let mut server = Server::new();
+    open(
+        &mut server,
+        "class Point {}\nvar origin = Point();\nprint origin;\n",
+    );
+
+    let hover = request(&mut server, "textDocument/hover", 2, 8);
+    let contents = hover.get("contents");
+    assert_eq!(contents.get("kind").as_str(), Some("markdown"));
+    assert_eq!(
+        contents.get("value").as_str(),
+        Some("```lox\nvar origin = Point();\n```\nglobal variable `origin`, declared on line 2")
+    );
+
+    let hover = request(&mut server, "textDocument/hover", 1, 14);
+    assert!(hover
+        .get("contents")
+        .get("value")
+        .as_str()
+        .unwrap()
+        .ends_with("class `Point`, declared on line 1"));
+}
+
+#[test]
+fn document_symbols_list_top_level_functions() {
+    let mut server = Server::new();
+    open(
+        &mut server,
+        "fun add(a, b) {\n  fun inner() {}\n  return a + b;\n}\nvar notAFunction = 1;\nexport fun twice(x) { return add(x, x); }\n",
+    );
+
+    let symbols = request(&mut server, "textDocument/documentSymbol", 0, 0);
+    let symbols = symbols.as_array().unwrap();
+    assert_eq!(symbols.len(), 2);
+
+    assert_eq!(symbols[0].get("name").as_str(), Some("add"));
+    assert_eq!(symbols[0].get("detail").as_str(), Some("(a, b)"));
+    assert_eq!(symbols[0].get("kind").as_usize(), Some(12));
+    assert_eq!(span(symbols[0].get("selectionRange")), (0, 4, 7));
+    let range = symbols[0].get("range");
+    assert_eq!(range.get("start").get("line").as_usize(), Some(0));
+    assert_eq!(range.get("end").get("line").as_usize(), Some(3));
+    assert_eq!(range.get("end").get("character").as_usize(), Some(1));
+
+    assert_eq!(symbols[1].get("name").as_str(), Some("twice"));
+    assert_eq!(span(symbols[1].get("range")), (5, 0, 41));
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    let mut server = Server::new();
+    open(&mut server, "var a = \"héllo 😀\"; var b = a;\n");
+
+    // "var a = "héllo 😀"; var b = " is 28 UTF-16 code units long
+    let definition = request(&mut server, "textDocument/definition", 0, 28);
+    assert_eq!(span(definition.get("range")), (0, 4, 5));
+    let references = request(&mut server, "textDocument/references", 0, 4);
+    assert_eq!(spans(&references), vec![(0, 4, 5), (0, 28, 29)]);
+}
+
+#[test]
+fn serves_framed_json_rpc_until_exit() {
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","text":"print 1"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/formatting","params":{}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ];
+    let input: String = messages
+        .iter()
+        .map(|body| format!("Content-Length: {}\r\n\r\n{body}", body.len()))
+        .collect();
+    let mut output = Vec::new();
+
+    let code = lsp::serve(Cursor::new(input), &mut output).unwrap();
+    assert_eq!(code, 0);
+
+    let mut output = Cursor::new(output);
+    let mut replies = Vec::new();
+    while let Some(body) = lsp::read_message(&mut output).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    assert_eq!(replies.len(), 4);
+
+    let capabilities = replies[0].get("result").get("capabilities");
+    assert_eq!(capabilities.get("definitionProvider"), &Json::Bool(true));
+    assert_eq!(capabilities.get("textDocumentSync").as_usize(), Some(1));
+
+    let diagnostics = replies[1]
+        .get("params")
+        .get("diagnostics")
+        .as_array()
+        .unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].get("source").as_str(), Some("rlox"));
+
+    assert_eq!(replies[2].get("id").as_usize(), Some(2));
+    assert_eq!(replies[2].get("error").get("code"), &Json::Number(-32601.0));
+    assert_eq!(replies[3].get("id").as_usize(), Some(3));
+    assert!(replies[3].get("result").is_null());
+}
+
+#[test]
+fn headers_without_a_content_length_are_rejected() {
+    for input in [
+        "Content-Type: x\r\n\r\n{}",
+        "Content-Length: many\r\n\r\n{}",
+    ] {
+        let err = lsp::read_message(&mut Cursor::new(input)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{input}");
+    }
+    assert!(lsp::serve(Cursor::new("\r\n"), Vec::new()).is_err());
+}
+
+#[test]
+fn json_round_trips_escapes() {
+    let text = r#"{"a":[1,2.5,-3e2,true,null],"s":"q\"\\\n\té😀/"}"#;
+    let json = Json::parse(text).unwrap();
+    assert_eq!(json.get("s").as_str(), Some("q\"\\\n\té😀/"));
+    assert_eq!(json.get("a").as_array().unwrap()[2], Json::Number(-300.0));
+    assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+    assert!(Json::parse("{\"a\":}").is_err());
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::fs;
+
+use common::{run_script, temp_module_dir};
+use rlox::Lox;
+
+#[test]
+fn imports_expose_only_exported_names() {
+    let dir = temp_module_dir(&[
+        (
+            "main.lox",
+            r#"
+            import { square, calls } from "lib/math";
+            import "lib/math.lox";
+            print square(3);
+            print calls();
+            print pi;
+            print helper;
+            "#,
+        ),
+        (
+            "lib/math.lox",
+            r#"
+            import { offset } from "base";
+            var count = 0;
+            fun helper(x) { return x * x; }
+            export fun square(x) { count = count + 1; return helper(x); }
+            export fun calls() { return count; }
+            export var pi = 3 + offset;
+            print "loading math";
+            "#,
+        ),
+        ("lib/base.lox", "export var offset = 0.14;"),
+    ]);
+
+    let (stdout, stderr) = run_script(&dir.join("main.lox"));
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines, vec!["\"loading math\"", "9", "1", "3.14"]);
+    assert!(stderr.contains("Undefined Variable 'helper'"));
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn module_errors_are_reported_at_the_import() {
+    let dir = temp_module_dir(&[
+        ("a.lox", "import \"b\";"),
+        ("b.lox", "import \"a\";"),
+        ("missing.lox", "import \"nowhere\";"),
+        ("unexported.lox", "import { hidden } from \"lib\";"),
+        ("lib.lox", "var hidden = 1;"),
+        ("broken.lox", "import \"bad\";"),
+        ("bad.lox", "print 1 +;"),
+    ]);
+
+    let (_stdout, stderr) = run_script(&dir.join("a.lox"));
+    assert!(stderr.contains("Cyclic import"), "{stderr}");
+    assert!(stderr.contains("a.lox -> "), "{stderr}");
+
+    let (_stdout, stderr) = run_script(&dir.join("missing.lox"));
+    assert!(stderr.contains("Could not load module"), "{stderr}");
+
+    let (_stdout, stderr) = run_script(&dir.join("unexported.lox"));
+    assert!(stderr.contains("has no export 'hidden'"), "{stderr}");
+
+    let (_stdout, stderr) = run_script(&dir.join("broken.lox"));
+    assert!(stderr.contains("Error in module 'bad'"), "{stderr}");
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn imports_and_exports_must_be_top_level() {
+    for source in [
+        "fun f() { import \"lib\"; }",
+        "{ export var x = 1; }",
+    ] {
+        let errors = Lox::new().compile(source).unwrap_err();
+        assert!(errors[0].message().contains("at the top level"), "{source}");
+    }
+}
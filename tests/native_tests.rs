@@ -0,0 +1,90 @@
+mod common;
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use common::run_in;
+use rlox::{
+    error::RuntimeSignal,
+    interpreter::{callable::Arity, values::Value, Interpreter},
+};
+
+#[test]
+fn natives_capture_host_state() {
+    let recorded = Rc::new(RefCell::new(Vec::new()));
+    let sink = recorded.clone();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("record", 1, move |_, args| {
+        sink.borrow_mut().push(args[0].as_number());
+        Ok(Value::Nil)
+    });
+
+    run_in(
+        &mut interpreter,
+        "for (var i = 0; i < 3; i = i + 1) record(i * 10);",
+    );
+
+    assert_eq!(*recorded.borrow(), vec![0.0, 10.0, 20.0]);
+}
+
+#[test]
+fn natives_run_on_the_hosts_thread() {
+    thread_local! {
+        static HOST_VALUE: Cell<f64> = const { Cell::new(0.0) };
+    }
+    HOST_VALUE.with(|value| value.set(42.0));
+
+    let seen = Rc::new(Cell::new(0.0));
+    let sink = seen.clone();
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("host", 0, move |_, _| {
+        sink.set(HOST_VALUE.with(Cell::get));
+        Ok(Value::Nil)
+    });
+
+    run_in(&mut interpreter, "host();");
+    assert_eq!(seen.get(), 42.0);
+}
+
+#[test]
+fn variadic_natives_receive_every_argument() {
+    let counts = Rc::new(RefCell::new(Vec::new()));
+    let sink = counts.clone();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("count", Arity::AtLeast(1), move |_, args| {
+        sink.borrow_mut().push(args.len());
+        Ok(Value::Number(args.len() as f64))
+    });
+
+    run_in(&mut interpreter, "count(1); count(1, 2, 3); count();");
+
+    // the zero argument call fails the arity check before reaching the native
+    assert_eq!(*counts.borrow(), vec![1, 3]);
+}
+
+#[test]
+fn natives_can_use_the_interpreter() {
+    let seen = Rc::new(RefCell::new(None));
+    let sink = seen.clone();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native("install", 0, move |interpreter, _| {
+        let sink = sink.clone();
+        interpreter.define_native("report", 1, move |_, args| {
+            *sink.borrow_mut() = Some(args[0].as_string());
+            Ok(Value::Nil)
+        });
+        Ok(Value::Nil)
+    });
+    interpreter.define_native("fail", 0, |_, _| {
+        Err(RuntimeSignal::native_error("host refused".into()))
+    });
+
+    run_in(&mut interpreter, "install(); report(\"ok\"); fail(); report(\"unreached\");");
+
+    assert_eq!(seen.borrow().as_deref(), Some("\"ok\""));
+}
@@ -136,3 +136,206 @@ fn parser_error_paths_are_static_errors() {
         assert!(errors.iter().all(is_static_error));
     }
 }
+
+#[test]
+fn parses_classes_and_property_access() {
+    let (statements, errors) = parse_source(
+        r#"
+        class Point {
+          init(x) { this.x = x; }
+          get() { return this.x; }
+        }
+        p.x;
+        p.x = 1;
+        p.a.b();
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert_eq!(statements.len(), 4);
+
+    assert!(matches!(
+        &statements[0],
+        Stmt::Class(class_def) if class_def.methods.len() == 2
+    ));
+    assert!(matches!(
+        statements[1],
+        Stmt::Expression(Expr::Get { .. })
+    ));
+    assert!(matches!(
+        statements[2],
+        Stmt::Expression(Expr::Set { .. })
+    ));
+    assert!(matches!(
+        &statements[3],
+        Stmt::Expression(Expr::Call { callee, .. }) if matches!(**callee, Expr::Get { .. })
+    ));
+}
+
+#[test]
+fn parses_break_continue_and_labeled_loops() {
+    let (statements, errors) = parse_source(
+        r#"
+        outer: while (true) { break outer; }
+        for (var i = 0; i < 1; i = i + 1) continue;
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert_eq!(statements.len(), 2);
+
+    assert!(matches!(
+        &statements[0],
+        Stmt::While(conditions)
+            if conditions.label.as_ref().is_some_and(|l| l.lexeme == "outer")
+                && matches!(&*conditions.stmt_body, Stmt::Block(body) if matches!(body[0], Stmt::Break(_, Some(_))))
+    ));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Block(stmts)
+            if matches!(stmts.get(1), Some(Stmt::While(conditions))
+                if conditions.increment.is_some()
+                    && matches!(*conditions.stmt_body, Stmt::Continue(_, None)))
+    ));
+}
+
+#[test]
+fn label_must_precede_a_loop() {
+    let (_statements, errors) = parse_source("outer: print 1;");
+    assert_eq!(errors.len(), 1);
+    assert!(is_static_error(&errors[0]));
+}
+
+#[test]
+fn parses_list_literals_and_indexing() {
+    let (statements, errors) = parse_source(
+        r#"
+        [];
+        [1, "a", [2]];
+        xs[0];
+        xs[0][1] = 2;
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert!(matches!(
+        &statements[0],
+        Stmt::Expression(Expr::List { elements, .. }) if elements.is_empty()
+    ));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Expression(Expr::List { elements, .. }) if elements.len() == 3
+    ));
+    assert!(matches!(
+        statements[2],
+        Stmt::Expression(Expr::Index { .. })
+    ));
+    assert!(matches!(
+        &statements[3],
+        Stmt::Expression(Expr::IndexSet { object, .. }) if matches!(**object, Expr::Index { .. })
+    ));
+}
+
+#[test]
+fn parses_map_literals() {
+    let (statements, errors) = parse_source(
+        r#"
+        var m = {};
+        var n = {"a": 1, 2: [3]};
+        m["a"] = n["a"];
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert!(matches!(
+        &statements[0],
+        Stmt::Var(_, Some(Expr::Map { entries, .. })) if entries.is_empty()
+    ));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Var(_, Some(Expr::Map { entries, .. })) if entries.len() == 2
+    ));
+    assert!(matches!(
+        statements[2],
+        Stmt::Expression(Expr::IndexSet { .. })
+    ));
+
+    let (_, errors) = parse_source("var m = {\"a\" 1};");
+    assert_eq!(errors.len(), 1);
+    assert!(is_static_error(&errors[0]));
+}
+
+#[test]
+fn parses_lambda_expressions() {
+    let (statements, errors) = parse_source(
+        r#"
+        var add = fun (a, b) { return a + b; };
+        fun () {}();
+        fun named() {}
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert!(matches!(
+        &statements[0],
+        Stmt::Var(_, Some(Expr::Lambda { fun_def, .. })) if fun_def.params.len() == 2
+    ));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Expression(Expr::Call { callee, .. }) if matches!(**callee, Expr::Lambda { .. })
+    ));
+    assert!(matches!(statements[2], Stmt::Function(_)));
+}
+
+#[test]
+fn parses_throw_and_try_statements() {
+    let (statements, errors) = parse_source(
+        r#"
+        throw "bad";
+        try { risky(); } catch (e) { print e; } finally { cleanup(); }
+        try { risky(); } finally { cleanup(); }
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert!(matches!(statements[0], Stmt::Throw(..)));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Try(try_stmt) if try_stmt.catch.is_some() && try_stmt.finally.is_some()
+    ));
+    assert!(matches!(
+        &statements[2],
+        Stmt::Try(try_stmt) if try_stmt.catch.is_none() && try_stmt.finally.is_some()
+    ));
+
+    let (_, errors) = parse_source("try { risky(); }");
+    assert_eq!(errors.len(), 1);
+    assert!(is_static_error(&errors[0]));
+}
+
+#[test]
+fn parses_imports_and_exports() {
+    let (statements, errors) = parse_source(
+        r#"
+        import "lib/math";
+        import { square, pi } from "lib/math.lox";
+        export fun square(x) { return x * x; }
+        export var pi = 3.14;
+        var from = 1;
+        "#,
+    );
+
+    assert!(errors.is_empty());
+    assert!(matches!(&statements[0], Stmt::Import(import) if import.names.is_none()));
+    assert!(matches!(
+        &statements[1],
+        Stmt::Import(import) if import.names.as_ref().is_some_and(|names| names.len() == 2)
+    ));
+    assert!(matches!(&statements[2], Stmt::Export(decl) if matches!(**decl, Stmt::Function(_))));
+    assert!(matches!(&statements[3], Stmt::Export(decl) if matches!(**decl, Stmt::Var(..))));
+
+    for source in ["export print 1;", "import { a } \"lib\";"] {
+        let (_, errors) = parse_source(source);
+        assert_eq!(errors.len(), 1, "{source}");
+    }
+}
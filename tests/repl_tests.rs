@@ -0,0 +1,46 @@
+mod common;
+
+use common::{repl_lines, run_repl, stderr_text};
+
+#[test]
+fn repl_keeps_state_between_lines() {
+    let lines = repl_lines("var x = 1;\nprint x;\nfun double(n) { return n * 2; }\nprint double(x);\n");
+    assert_eq!(lines, vec!["1", "2"]);
+}
+
+#[test]
+fn repl_echoes_bare_expressions() {
+    let lines = repl_lines("1 + 2;\nvar a = 4;\na * a;\n");
+    assert_eq!(lines, vec!["3", "16"]);
+}
+
+#[test]
+fn repl_accepts_multi_line_input_until_balanced() {
+    let lines = repl_lines("fun add(a,\n b) {\n  return a + b;\n}\nadd(\n 1, 2\n);\n");
+    assert_eq!(lines, vec!["3"]);
+}
+
+#[test]
+fn repl_continues_list_and_map_literals_until_balanced() {
+    let lines = repl_lines("var l = [\n1,\n2];\nprint len(l);\nvar m = {\n\"a\": [1,\n2]\n};\nprint m;\n");
+    assert_eq!(lines, vec!["2", "{\"a\": [1, 2]}"]);
+}
+
+#[test]
+fn repl_keeps_running_after_literals_cut_short() {
+    // balanced, yet the list is still open when the line ends
+    for literal in ["[", "print {", "print {1:"] {
+        let output = run_repl(&format!(");{literal}\nprint 3;\n"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{literal}");
+        assert!(stdout.contains("Expect expression before end of input"), "{stdout}");
+        assert!(stdout.contains('3'), "{literal}");
+    }
+}
+
+#[test]
+fn repl_keeps_running_after_errors() {
+    let output = run_repl("print missing;\nvar y = 3;\nprint y;\n");
+    assert!(stderr_text(&output).contains("Undefined Variable"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains('3'));
+}
@@ -1,6 +1,6 @@
 mod common;
 
-use rlox::scanner::token_type::TokenType::*;
+use rlox::scanner::{token_type::TokenType::*, Scanner};
 
 use common::{is_static_error, scan_types};
 
@@ -71,3 +71,85 @@ fn scanner_skips_comments() {
     assert!(errors.is_empty());
     assert_eq!(types, vec![Plus, Slash, EOF]);
 }
+
+#[test]
+fn tokens_carry_offset_column_and_length() {
+    let mut scanner = Scanner::new("var ab = 1;\n  print \"hi\";".to_string());
+    let (tokens, errors) = scanner.scan_tokens();
+    assert!(errors.is_empty());
+
+    let spans: Vec<_> = tokens
+        .iter()
+        .map(|t| (t.line, t.column, t.offset, t.length))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (1, 1, 0, 3),
+            (1, 5, 4, 2),
+            (1, 8, 7, 1),
+            (1, 10, 9, 1),
+            (1, 11, 10, 1),
+            (2, 3, 14, 5),
+            (2, 9, 20, 4),
+            (2, 13, 24, 1),
+            (2, 14, 25, 0),
+        ]
+    );
+}
+
+#[test]
+fn scans_loop_control_tokens() {
+    let (types, errors) = scan_types("outer: break continue");
+    assert!(errors.is_empty());
+    assert_eq!(types, vec![Identifier, Colon, Break, Continue, EOF]);
+}
+
+#[test]
+fn scans_brackets() {
+    let (types, errors) = scan_types("xs[0] = [];");
+    assert!(errors.is_empty());
+    assert_eq!(
+        types,
+        vec![
+            Identifier,
+            LeftBracket,
+            Number,
+            RightBracket,
+            Equal,
+            LeftBracket,
+            RightBracket,
+            Semicolon,
+            EOF
+        ]
+    );
+}
+
+#[test]
+fn keeps_comments_and_blank_lines_as_trivia_on_request() {
+    use rlox::scanner::token::TriviaKind;
+
+    let source = "var a; // note\n\n/* block\n */ print a;\n";
+    let mut scanner = Scanner::with_trivia(source.to_string());
+    let (tokens, errors) = scanner.scan_tokens();
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 7);
+
+    let trivia: Vec<_> = scanner
+        .trivia()
+        .iter()
+        .map(|trivia| (trivia.kind, trivia.text.as_str(), trivia.line))
+        .collect();
+    assert_eq!(
+        trivia,
+        vec![
+            (TriviaKind::LineComment, "// note", 1),
+            (TriviaKind::BlankLine, "", 2),
+            (TriviaKind::BlockComment, "/* block\n */", 3),
+        ]
+    );
+
+    let mut plain = Scanner::new(source.to_string());
+    plain.scan_tokens();
+    assert!(plain.trivia().is_empty());
+}